@@ -0,0 +1,59 @@
+use std::{fmt::Debug, ops::Add};
+
+use crate::utils::NodeValue;
+
+/// Monoid used to aggregate the values of a subtree.
+///
+/// Each node of a tree with an aggregate keeps the combination of all the values under it, so the
+/// aggregate of a prefix is read in O(|prefix|). An empty subtree is the identity and aggregates to
+/// `None`, so only the associative combination of two values has to be provided.
+pub trait Monoid<T>: Debug + Send + Sync
+where
+    T: NodeValue,
+{
+    /// Combines two aggregated values, the operation must be associative.
+    fn combine(&self, a: &T, b: &T) -> T;
+}
+
+/// Aggregates the values by adding them up.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Sum;
+
+impl<T: NodeValue + Add<Output = T>> Monoid<T> for Sum {
+    fn combine(&self, a: &T, b: &T) -> T {
+        a.clone() + b.clone()
+    }
+}
+
+/// Aggregates the values by keeping the largest one.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Max;
+
+impl<T: NodeValue + Ord> Monoid<T> for Max {
+    fn combine(&self, a: &T, b: &T) -> T {
+        a.max(b).clone()
+    }
+}
+
+/// Aggregates the values by keeping the smallest one.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Min;
+
+impl<T: NodeValue + Ord> Monoid<T> for Min {
+    fn combine(&self, a: &T, b: &T) -> T {
+        a.min(b).clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_monoids() {
+        assert_eq!(Sum.combine(&2, &3), 5);
+        assert_eq!(Sum.combine(&1.5, &2.0), 3.5);
+        assert_eq!(Max.combine(&2, &3), 3);
+        assert_eq!(Min.combine(&"b", &"a"), "a");
+    }
+}
@@ -0,0 +1,244 @@
+use std::{borrow::Cow, cmp::Ordering, fmt::Debug};
+
+use crate::utils::EdgeLabel;
+
+/// Collation controls how keys are matched and ordered in a tree.
+///
+/// Keys are normalized before they are used to navigate the tree, while the leaf keeps the key
+/// exactly as it was inserted. Edge labels are ordered with [`Collation::compare`].
+pub trait Collation<L>: Debug + Send + Sync
+where
+    L: EdgeLabel,
+{
+    /// Normalizes the given key, keys with the same normalized form are considered equal.
+    fn normalize<'a>(&self, key: &'a [L]) -> Cow<'a, [L]> {
+        Cow::Borrowed(key)
+    }
+
+    /// Compares two edge labels of the normalized keys.
+    fn compare(&self, a: &L, b: &L) -> Ordering {
+        a.cmp(b)
+    }
+
+    /// Compares two normalized keys label by label, a key sorts before the keys it prefixes.
+    fn compare_keys(&self, a: &[L], b: &[L]) -> Ordering {
+        a.iter()
+            .zip(b)
+            .map(|(a, b)| self.compare(a, b))
+            .find(|ordering| ordering.is_ne())
+            .unwrap_or_else(|| a.len().cmp(&b.len()))
+    }
+}
+
+/// The default collation, which matches keys exactly and orders them by the raw label order.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Binary;
+
+impl<L: EdgeLabel> Collation<L> for Binary {}
+
+/// Case-insensitive collation, keys are matched and ordered by their lowercase form.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CaseInsensitive;
+
+impl Collation<u8> for CaseInsensitive {
+    fn normalize<'a>(&self, key: &'a [u8]) -> Cow<'a, [u8]> {
+        if key.iter().any(u8::is_ascii_uppercase) {
+            Cow::Owned(key.to_ascii_lowercase())
+        } else {
+            Cow::Borrowed(key)
+        }
+    }
+}
+
+impl Collation<char> for CaseInsensitive {
+    fn normalize<'a>(&self, key: &'a [char]) -> Cow<'a, [char]> {
+        if key.iter().any(|c| c.is_uppercase()) {
+            Cow::Owned(key.iter().flat_map(|c| c.to_lowercase()).collect())
+        } else {
+            Cow::Borrowed(key)
+        }
+    }
+}
+
+impl Collation<String> for CaseInsensitive {
+    fn normalize<'a>(&self, key: &'a [String]) -> Cow<'a, [String]> {
+        if key.iter().any(|s| s.chars().any(char::is_uppercase)) {
+            Cow::Owned(key.iter().map(|s| s.to_lowercase()).collect())
+        } else {
+            Cow::Borrowed(key)
+        }
+    }
+}
+
+/// Natural collation, runs of ASCII digits are ordered by their numeric value so `file2` sorts
+/// before `file10`.
+///
+/// Digit runs are re-encoded during normalization as `len(digits) digits len(leading zeros)`, where
+/// every part only uses digit symbols. The encoded form keeps digits sorting before letters while
+/// making the raw label order numeric. Numbers that only differ by leading zeros are distinct keys
+/// and sort by the number of leading zeros.
+///
+/// As prefixes are normalized as well, a prefix ending in the middle of a number does not match
+/// longer numbers, e.g. `file1` is not a prefix of `file10`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Natural;
+
+impl Natural {
+    /// Encodes a length so that the encoding only uses digits and sorts by the length.
+    fn encode_len<L>(mut len: usize, digit: impl Fn(u8) -> L, normalized: &mut Vec<L>) {
+        while len >= 9 {
+            normalized.push(digit(9));
+            len -= 9;
+        }
+        normalized.push(digit(len as u8));
+    }
+
+    /// Re-encodes all digit runs of the key, `value_of` returns the value of a digit symbol.
+    fn normalize_digits<'a, L: EdgeLabel>(
+        key: &'a [L],
+        value_of: impl Fn(&L) -> Option<u8>,
+        digit: impl Fn(u8) -> L,
+    ) -> Cow<'a, [L]> {
+        if !key.iter().any(|l| value_of(l).is_some()) {
+            return Cow::Borrowed(key);
+        }
+
+        let mut normalized = Vec::with_capacity(key.len() + 4);
+        let mut i = 0;
+        while i < key.len() {
+            if value_of(&key[i]).is_none() {
+                normalized.push(key[i].clone());
+                i += 1;
+                continue;
+            }
+
+            let start = i;
+            while i < key.len() && value_of(&key[i]).is_some() {
+                i += 1;
+            }
+            let run = &key[start..i];
+            let leading_zeros = run.iter().take_while(|l| value_of(l) == Some(0)).count();
+            let significant = &run[leading_zeros..];
+
+            Self::encode_len(significant.len(), &digit, &mut normalized);
+            normalized.extend_from_slice(significant);
+            Self::encode_len(leading_zeros, &digit, &mut normalized);
+        }
+        Cow::Owned(normalized)
+    }
+}
+
+impl Collation<u8> for Natural {
+    fn normalize<'a>(&self, key: &'a [u8]) -> Cow<'a, [u8]> {
+        Self::normalize_digits(key, |b| b.is_ascii_digit().then(|| b - b'0'), |d| b'0' + d)
+    }
+}
+
+impl Collation<char> for Natural {
+    fn normalize<'a>(&self, key: &'a [char]) -> Cow<'a, [char]> {
+        Self::normalize_digits(
+            key,
+            |c| c.to_digit(10).map(|d| d as u8),
+            |d| char::from(b'0' + d),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_binary() {
+        assert_eq!(Binary.normalize(b"Key".as_slice()), Cow::Borrowed(b"Key"));
+        assert_eq!(
+            Collation::<u8>::compare(&Binary, &b'a', &b'b'),
+            Ordering::Less
+        );
+        assert_eq!(
+            Collation::<u8>::compare(&Binary, &b'B', &b'a'),
+            Ordering::Less
+        );
+    }
+
+    #[test]
+    fn test_case_insensitive() {
+        assert!(matches!(
+            CaseInsensitive.normalize(b"key".as_slice()),
+            Cow::Borrowed(_)
+        ));
+        assert_eq!(
+            CaseInsensitive.normalize(b"KeY".as_slice()).as_ref(),
+            b"key"
+        );
+
+        let chars = "ÄbC".chars().collect::<Vec<_>>();
+        assert_eq!(
+            CaseInsensitive.normalize(&chars).as_ref(),
+            ['ä', 'b', 'c'].as_slice()
+        );
+
+        let segments = vec!["Usr".to_string(), "local".to_string()];
+        assert_eq!(
+            CaseInsensitive.normalize(&segments).as_ref(),
+            ["usr".to_string(), "local".to_string()].as_slice()
+        );
+    }
+
+    #[test]
+    fn test_natural() {
+        let normalize = |key: &str| Natural.normalize(key.as_bytes()).into_owned();
+
+        assert!(matches!(
+            Natural.normalize(b"file".as_slice()),
+            Cow::Borrowed(_)
+        ));
+        assert_eq!(normalize("file2"), b"file120");
+        assert_eq!(normalize("file10"), b"file2100");
+        assert_eq!(normalize("file007"), b"file172");
+        assert_eq!(normalize("0"), b"01");
+        assert_eq!(normalize("v1.20"), b"v110.2200");
+        assert_eq!(normalize("1234567890"), b"9112345678900");
+
+        let mut keys = vec![
+            "file10",
+            "file2",
+            "file1",
+            "file02",
+            "file",
+            "file1a",
+            "fileA",
+            "file0",
+            "file100",
+            "file9",
+            "12345678901",
+            "999999999",
+            "x",
+        ];
+        keys.sort_by_key(|key| normalize(key));
+        assert_eq!(
+            keys,
+            vec![
+                "999999999",
+                "12345678901",
+                "file",
+                "file0",
+                "file1",
+                "file1a",
+                "file2",
+                "file02",
+                "file9",
+                "file10",
+                "file100",
+                "fileA",
+                "x",
+            ]
+        );
+
+        let chars = "файл12".chars().collect::<Vec<_>>();
+        assert_eq!(
+            Natural.normalize(&chars).as_ref(),
+            "файл2120".chars().collect::<Vec<_>>().as_slice()
+        );
+    }
+}
@@ -1,8 +1,11 @@
 #![allow(dead_code)]
 #![feature(trait_alias)]
 
+pub mod aggregate;
+pub mod collation;
 pub mod node;
 pub mod tree;
+pub mod watch;
 
 mod utils;
-pub use utils::NodeValue;
+pub use utils::{EdgeLabel, NodeValue};
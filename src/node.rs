@@ -1,309 +1,544 @@
+mod iterator;
 #[cfg(test)]
 mod node_test;
-
-use std::{hash::Hash, sync::Arc};
-
-use parking_lot::RwLock;
-
-use crate::utils::NodeValue;
-
-#[derive(Debug, Default, Hash, Eq)]
-pub struct Edge<T>
+mod path_iterator;
+mod prefix;
+
+use std::{cmp::Ordering, hash::Hash, mem, sync::Arc};
+
+pub(crate) use self::prefix::Prefix;
+pub use self::{
+    iterator::{Iter, Range},
+    path_iterator::PathIterator,
+};
+use crate::{
+    aggregate::Monoid,
+    collation::Collation,
+    utils::{EdgeLabel, NodeValue},
+    watch::{Signal, Watch},
+};
+
+#[derive(Debug, Default, Eq)]
+pub struct Edge<T, L = u8>
 where
     T: NodeValue,
+    L: EdgeLabel,
 {
-    label: u8,
-    node: Arc<Node<T>>,
+    label: L,
+    node: Arc<Node<T, L>>,
 }
 
-impl<T: NodeValue> Edge<T> {
+impl<T: NodeValue, L: EdgeLabel> Edge<T, L> {
     /// Creates a new edge with the given label and node.
-    pub(crate) fn new(label: u8, node: Arc<Node<T>>) -> Self {
+    pub(crate) fn new(label: L, node: Arc<Node<T, L>>) -> Self {
         Self { label, node }
     }
 
+    /// Get the label of the edge.
+    pub(crate) fn get_label(&self) -> &L {
+        &self.label
+    }
+
     /// Get the node of the edge.
-    pub(crate) fn get_node(&self) -> &Node<T> {
+    pub(crate) fn get_node(&self) -> &Node<T, L> {
         &self.node
     }
+
+    /// Returns the shared pointer to the node of the edge.
+    pub(crate) fn get_shared_node(&self) -> &Arc<Node<T, L>> {
+        &self.node
+    }
+
+    /// Takes the node out of the edge.
+    pub(crate) fn into_node(self) -> Arc<Node<T, L>> {
+        self.node
+    }
 }
 
-impl<T: NodeValue> Clone for Edge<T> {
+impl<T: NodeValue, L: EdgeLabel> Clone for Edge<T, L> {
     fn clone(&self) -> Self {
         Self {
-            label: self.label,
-            node: (*self.node).clone().into(),
+            label: self.label.clone(),
+            node: self.node.clone(),
         }
     }
 }
 
-impl<T: NodeValue> PartialOrd for Edge<T> {
+impl<T: NodeValue, L: EdgeLabel> Hash for Edge<T, L> {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.label.hash(state);
+        self.node.hash(state);
+    }
+}
+
+impl<T: NodeValue + PartialEq, L: EdgeLabel> PartialOrd for Edge<T, L> {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
         Some(self.label.cmp(&other.label))
     }
 }
 
-impl<T: NodeValue> PartialEq for Edge<T> {
+impl<T: NodeValue + PartialEq, L: EdgeLabel> PartialEq for Edge<T, L> {
     fn eq(&self, other: &Self) -> bool {
         self.label == other.label && self.node == other.node
     }
 }
 
-#[derive(Debug, Default)]
-pub struct Edges<T>(RwLock<Vec<Edge<T>>>)
+/// Capacities of the edge layouts, like the node kinds of an adaptive radix tree. The edges grow
+/// into the next layout when they are full, and shrink back once they fit a smaller one with room
+/// to spare.
+const LAYOUT_CAPACITIES: [usize; 4] = [4, 16, 48, 256];
+
+/// Lookup structure of the edges, chosen by their fanout.
+#[derive(Debug, Default, Clone)]
+enum EdgeIndex {
+    /// Up to 4 edges, scanned linearly.
+    #[default]
+    Node4,
+
+    /// Up to 16 edges, or edges which are not labelled by bytes, found by binary search.
+    Node16,
+
+    /// Up to 48 edges labelled by bytes, with one plus the index of the edge of each byte.
+    Node48(Box<[u8; 256]>),
+
+    /// More edges labelled by bytes in byte order, with the number of edges before each byte.
+    Node256(Box<[u8; 256]>),
+}
+
+/// The edges of a node, sorted by label.
+///
+/// Edges adapt their lookup structure to their fanout: small nodes are searched while large nodes
+/// with byte labels find an edge, or where it would be inserted, with a single table lookup.
+#[derive(Debug)]
+pub struct Edges<T, L = u8>(Vec<Edge<T, L>>, EdgeIndex)
 where
-    T: NodeValue;
+    T: NodeValue,
+    L: EdgeLabel;
 
-impl<T: NodeValue> Clone for Edges<T> {
+impl<T: NodeValue, L: EdgeLabel> Default for Edges<T, L> {
+    fn default() -> Self {
+        Self(Vec::new(), EdgeIndex::default())
+    }
+}
+
+impl<T: NodeValue, L: EdgeLabel> Clone for Edges<T, L> {
     fn clone(&self) -> Self {
-        Self(RwLock::new(self.0.read().clone()))
+        Self(self.0.clone(), self.1.clone())
+    }
+
+    fn clone_from(&mut self, source: &Self) {
+        self.0.clone_from(&source.0);
+        self.1.clone_from(&source.1);
+    }
+}
+
+impl<T: NodeValue, L: EdgeLabel> Drop for Edges<T, L> {
+    /// Drops the subtrees of the edges iteratively, as dropping the nested nodes one within the
+    /// other would overflow the stack on very deep trees. Nodes still shared are left to their
+    /// other owners.
+    fn drop(&mut self) {
+        if self.0.is_empty() {
+            return;
+        }
+
+        let mut nodes = Vec::new();
+        nodes.extend(self.0.drain(..).map(|edge| edge.node));
+        while let Some(mut node) = nodes.pop() {
+            if let Some(writable_node) = Arc::get_mut(&mut node) {
+                nodes.extend(writable_node.edges.0.drain(..).map(|edge| edge.node));
+            }
+        }
     }
 }
 
-impl<T: NodeValue> Hash for Edges<T> {
+impl<T: NodeValue, L: EdgeLabel> Hash for Edges<T, L> {
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
-        self.0.read().hash(state);
+        self.0.hash(state);
     }
 }
 
-impl<T: NodeValue> PartialEq for Edges<T> {
+impl<T: NodeValue + PartialEq, L: EdgeLabel> PartialEq for Edges<T, L> {
     fn eq(&self, other: &Self) -> bool {
-        self.0.read().as_slice().eq(other.0.read().as_slice())
+        self.0.as_slice().eq(other.0.as_slice())
     }
 }
 
-impl<T: NodeValue> From<Vec<Edge<T>>> for Edges<T> {
-    fn from(vec: Vec<Edge<T>>) -> Self {
-        Self(RwLock::new(vec))
+impl<T: NodeValue, L: EdgeLabel> From<Vec<Edge<T, L>>> for Edges<T, L> {
+    fn from(vec: Vec<Edge<T, L>>) -> Self {
+        let mut edges = Self(vec, EdgeIndex::default());
+        edges.reindex();
+        edges
     }
 }
 
-impl<T: NodeValue> Edges<T> {
+impl<T: NodeValue, L: EdgeLabel> Edges<T, L> {
+    /// Rebuilds the lookup structure for the current edges.
+    fn reindex(&mut self) {
+        let edges = &self.0;
+        let len = edges.len();
+        self.1 = match mem::take(&mut self.1) {
+            _ if len <= LAYOUT_CAPACITIES[0] => EdgeIndex::Node4,
+            _ if len <= LAYOUT_CAPACITIES[1] || edges[0].label.as_byte().is_none() => {
+                EdgeIndex::Node16
+            }
+            index if len <= LAYOUT_CAPACITIES[2] => {
+                let mut positions = match index {
+                    EdgeIndex::Node48(positions) => positions,
+                    _ => Box::new([0; 256]),
+                };
+                positions.fill(0);
+                for (edge_idx, edge) in edges.iter().enumerate() {
+                    positions[edge.label.as_byte().unwrap_or_default() as usize] =
+                        edge_idx as u8 + 1;
+                }
+                EdgeIndex::Node48(positions)
+            }
+            // ranks only locate the existing edges if they are in byte order
+            index if edges.is_sorted_by_key(|edge| edge.label.as_byte()) => {
+                let mut ranks = match index {
+                    EdgeIndex::Node256(ranks) => ranks,
+                    _ => Box::new([0; 256]),
+                };
+                let mut rank = 0;
+                for (byte, byte_rank) in ranks.iter_mut().enumerate() {
+                    *byte_rank = rank as u8;
+                    if rank < len && edges[rank].label.as_byte() == Some(byte as u8) {
+                        rank += 1;
+                    }
+                }
+                EdgeIndex::Node256(ranks)
+            }
+            _ => EdgeIndex::Node16,
+        };
+    }
+
+    /// Returns the index of the edge with the given label, or the index where it would be inserted.
+    fn search(&self, label: &L, collation: &dyn Collation<L>) -> Result<usize, usize> {
+        let binary_search = || {
+            self.0
+                .binary_search_by(|e| collation.compare(&e.label, label))
+        };
+        match &self.1 {
+            EdgeIndex::Node4 => {
+                for (edge_idx, edge) in self.0.iter().enumerate() {
+                    match collation.compare(&edge.label, label) {
+                        Ordering::Less => {}
+                        Ordering::Equal => return Ok(edge_idx),
+                        Ordering::Greater => return Err(edge_idx),
+                    }
+                }
+                Err(self.0.len())
+            }
+            EdgeIndex::Node16 => binary_search(),
+            EdgeIndex::Node48(_) => match self.position(label, collation) {
+                Some(edge_idx) => Ok(edge_idx),
+                None => binary_search(),
+            },
+            EdgeIndex::Node256(ranks) => {
+                let byte = label
+                    .as_byte()
+                    .expect("indexed edges are labelled by bytes");
+                let edge_idx = ranks[byte as usize] as usize;
+                match self.0.get(edge_idx) {
+                    Some(edge) if edge.label.as_byte() == Some(byte) => Ok(edge_idx),
+                    // the collation may not order the missing byte with the others
+                    _ => binary_search(),
+                }
+            }
+        }
+    }
+
+    /// Returns the index of the edge with the given label.
+    fn position(&self, label: &L, collation: &dyn Collation<L>) -> Option<usize> {
+        match &self.1 {
+            EdgeIndex::Node48(positions) => {
+                let byte = label
+                    .as_byte()
+                    .expect("indexed edges are labelled by bytes");
+                (positions[byte as usize] as usize).checked_sub(1)
+            }
+            _ => self.search(label, collation).ok(),
+        }
+    }
+
     /// Adds an edge to the edges while maintaining sorted order.
-    fn add_edge(&self, edge: Edge<T>) {
+    fn add_edge(&mut self, edge: Edge<T, L>, collation: &dyn Collation<L>) {
         let insert_idx = self
-            .0
-            .read()
-            .binary_search_by(|e| e.label.cmp(&edge.label))
+            .search(&edge.label, collation)
             .unwrap_or_else(|idx| idx);
-        self.0.write().insert(insert_idx, edge);
+        let len = self.0.len();
+        if len == self.0.capacity() {
+            let capacity = LAYOUT_CAPACITIES
+                .into_iter()
+                .find(|capacity| *capacity > len)
+                .unwrap_or(len * 2);
+            self.0.reserve_exact(capacity - len);
+        }
+        self.0.insert(insert_idx, edge);
+        self.reindex();
+    }
+
+    /// Releases the capacity of a larger layout once the edges fit a smaller one with room to spare.
+    fn shrink(&mut self) {
+        let len = self.0.len();
+        if let Some(capacity) = LAYOUT_CAPACITIES
+            .into_iter()
+            .find(|capacity| *capacity > len)
+            && capacity < self.0.capacity()
+        {
+            self.0.shrink_to(capacity);
+        }
+        self.reindex();
     }
 
     /// Replaces the node of the edge with the same label.
-    fn replace_edge(&self, edge: Edge<T>) {
-        let self_edges = self.0.read();
-        let self_edges_slice = self_edges.as_slice();
-        let edge_idx = self_edges_slice
-            .binary_search_by(|e| e.label.cmp(&edge.label))
-            .unwrap_or_else(|idx| idx);
-        if edge_idx < self_edges_slice.len() && self_edges_slice[edge_idx].label == edge.label {
-            drop(self_edges); // release read lock before acquiring write lock
-            self.0.write()[edge_idx].node = edge.node;
-        } else {
-            panic!("replace missing edge");
+    fn replace_edge(&mut self, edge: Edge<T, L>, collation: &dyn Collation<L>) {
+        match self.position(&edge.label, collation) {
+            Some(edge_idx) => self.0[edge_idx].node = edge.node,
+            None => panic!("replace missing edge"),
         }
     }
 
     /// Replaces the node of the edge at the given index.
-    fn replace_edge_at(&self, index: usize, edge: Edge<T>) {
-        let self_edges = self.0.read();
-        let self_edges_slice = self_edges.as_slice();
-        if index < self_edges_slice.len() && self_edges_slice[index].label == edge.label {
-            drop(self_edges); // release read lock before acquiring write lock
-            self.0.write()[index].node = edge.node;
+    fn replace_edge_at(&mut self, index: usize, edge: Edge<T, L>) {
+        if index < self.0.len() && self.0[index].label == edge.label {
+            self.0[index].node = edge.node;
         } else {
             panic!("replace edge at invalid index or label mismatch");
         }
     }
 
+    /// Returns the index and a reference to the node of the edge with the given label.
+    fn find_edge(
+        &self,
+        label: &L,
+        collation: &dyn Collation<L>,
+    ) -> Option<(usize, &Arc<Node<T, L>>)> {
+        let edge_idx = self.position(label, collation)?;
+        Some((edge_idx, &self.0[edge_idx].node))
+    }
+
     /// Returns the index and node of the edge with the given label.
-    fn get_edge(&self, label: u8) -> Option<(usize, Arc<Node<T>>)> {
-        let self_edges = self.0.read();
-        let edge_idx = self_edges
-            .as_slice()
-            .binary_search_by(|e| e.label.cmp(&label))
-            .unwrap_or_else(|idx| idx);
-        if edge_idx < self_edges.len() && self_edges[edge_idx].label == label {
-            let node = self_edges[edge_idx].node.clone();
-            Some((edge_idx, node))
-        } else {
-            None
-        }
+    fn get_edge(
+        &self,
+        label: &L,
+        collation: &dyn Collation<L>,
+    ) -> Option<(usize, Arc<Node<T, L>>)> {
+        self.find_edge(label, collation)
+            .map(|(edge_idx, node)| (edge_idx, node.clone()))
     }
 
     /// Returns the node of the edge at the given index.
-    fn get_edge_at(&self, index: usize) -> Option<Arc<Node<T>>> {
-        let self_edges = self.0.read();
-        if index < self_edges.len() {
-            Some(self_edges[index].node.clone())
-        } else {
-            None
-        }
+    fn get_edge_at(&self, index: usize) -> Option<Arc<Node<T, L>>> {
+        self.0.get(index).map(|edge| edge.node.clone())
+    }
+
+    /// Returns a mutable reference to the node of the edge at the given index.
+    fn edge_node_mut(&mut self, index: usize) -> &mut Arc<Node<T, L>> {
+        &mut self.0[index].node
     }
 
     /// Returns the index and node of the lowest edge with label >= given label.
-    fn get_lower_bound_edge(&self, label: u8) -> Option<(usize, Arc<Node<T>>)> {
-        let self_edges = self.0.read();
-        let edge_idx = self_edges
-            .as_slice()
-            .binary_search_by(|e| e.label.cmp(&label))
-            .unwrap_or_else(|idx| idx);
-        if edge_idx < self_edges.len() {
-            let node = self_edges[edge_idx].node.clone();
-            Some((edge_idx, node))
-        } else {
-            None
-        }
+    fn get_lower_bound_edge(
+        &self,
+        label: &L,
+        collation: &dyn Collation<L>,
+    ) -> Option<(usize, Arc<Node<T, L>>)> {
+        let edge_idx = self.search(label, collation).unwrap_or_else(|idx| idx);
+        self.get_edge_at(edge_idx).map(|node| (edge_idx, node))
     }
 
     /// Deletes the edge with the given label.
-    fn delete_edge(&self, label: u8) {
-        let self_edges = self.0.read();
-        let self_edges_slice = self_edges.as_slice();
-        let edge_idx = self_edges_slice
-            .binary_search_by(|e| e.label.cmp(&label))
-            .unwrap_or_else(|idx| idx);
-        if edge_idx < self_edges_slice.len() && self_edges_slice[edge_idx].label == label {
-            drop(self_edges); // release read lock before acquiring write lock
-            self.0.write().remove(edge_idx);
+    fn delete_edge(&mut self, label: &L, collation: &dyn Collation<L>) {
+        if let Some(edge_idx) = self.position(label, collation) {
+            self.delete_edge_at(edge_idx);
         }
     }
 
+    /// Deletes the edge at the given index.
+    fn delete_edge_at(&mut self, index: usize) {
+        self.0.remove(index);
+        self.shrink();
+    }
+
     /// Returns true if there are no edges.
     fn is_empty(&self) -> bool {
-        self.0.read().is_empty()
+        self.0.is_empty()
     }
 
     /// Returns the number of edges.
     fn len(&self) -> usize {
-        self.0.read().len()
+        self.0.len()
     }
 
     /// Returns the first edge's node if exists.
-    fn first(&self) -> Option<Arc<Node<T>>> {
-        let self_edges = self.0.read();
-        if !self_edges.is_empty() {
-            Some(self_edges[0].node.clone())
-        } else {
-            None
-        }
+    fn first(&self) -> Option<Arc<Node<T, L>>> {
+        self.0.first().map(|edge| edge.node.clone())
     }
 
     /// Returns the last edge's node if exists.
-    fn last(&self) -> Option<Arc<Node<T>>> {
-        let self_edges = self.0.read();
-        if !self_edges.is_empty() {
-            Some(self_edges[self_edges.len() - 1].node.clone())
-        } else {
-            None
-        }
+    fn last(&self) -> Option<Arc<Node<T, L>>> {
+        self.0.last().map(|edge| edge.node.clone())
     }
 
     /// Removes all edges data.
-    fn clear(&self) {
-        self.0.write().clear();
+    fn clear(&mut self) {
+        self.0.clear();
+        self.reindex();
     }
 
     /// Removes all edges and resets allocated capacity.
-    fn reset(&self) {
-        self.0.write().clear();
-        *self.0.write() = Vec::new();
+    fn reset(&mut self) {
+        *self = Self::default();
     }
 
     /// Removes the last edge and returns it if exists.
-    fn pop(&self) -> Option<Edge<T>> {
-        self.0.write().pop()
+    fn pop(&mut self) -> Option<Edge<T, L>> {
+        let edge = self.0.pop();
+        self.shrink();
+        edge
     }
 
-    /// Drains all edges from self and inserts them into other
-    fn collect_into(&self, other: &Edges<T>) {
-        let mut self_guard = self.0.write();
-        let mut other_guard = other.0.write();
+    /// Removes all edges while keeping their allocation, moving their nodes into the given vector.
+    fn release_into(&mut self, nodes: &mut Vec<Arc<Node<T, L>>>) {
+        nodes.extend(self.0.drain(..).map(|edge| edge.node));
+        self.reindex();
+    }
 
-        let self_len = self_guard.len();
-        let other_capacity = other_guard.capacity();
-        if other_capacity < self_len {
-            other_guard.reserve(self_len - other_capacity);
-        }
+    /// Copies all edges from self into other, sharing the child nodes.
+    fn collect_into(&self, other: &mut Edges<T, L>) {
+        other.0.extend(self.0.iter().cloned());
+        other.reindex();
+    }
 
-        let self_iter = self_guard.drain(..).into_iter();
-        other_guard.extend(self_iter);
+    /// Returns the edges as a slice in order.
+    fn as_slice(&self) -> &[Edge<T, L>] {
+        &self.0
     }
 
     /// Iterates over each edge and applies the given function
     fn for_each<F>(&self, f: F)
     where
-        F: FnMut(&Edge<T>),
+        F: FnMut(&Edge<T, L>),
     {
-        self.0.read().iter().for_each(f);
+        self.0.iter().for_each(f);
     }
 }
 
 /// An immutable node in the radix tree, which may contains a value if it is a leaf node.
 /// It also contains edges to its child nodes if exists.
-#[derive(Debug, Default)]
-pub struct Node<T>
+///
+/// Nodes are never locked: a node is only modified by the transaction which exclusively owns it,
+/// and shared nodes are copied before being modified.
+#[derive(Debug)]
+pub struct Node<T, L = u8>
 where
     T: NodeValue,
+    L: EdgeLabel,
 {
-    // TODO: add Node update signal
-    // TODO: optimise this with Vec<u8>
-
-    // prefix ignored
-    pub(crate) prefix: RwLock<String>,
+    // prefix ignored, stored inline when short
+    pub(crate) prefix: Prefix<L>,
 
     // used to store possible leaf
-    pub(crate) leaf: RwLock<Option<Arc<LeafNode<T>>>>,
+    pub(crate) leaf: Option<Arc<LeafNode<T, L>>>,
 
     // edges to child nodes
-    pub(crate) edges: Edges<T>,
+    pub(crate) edges: Edges<T, L>,
+
+    // aggregate of all the values in the subtree, only maintained when the tree has an aggregate
+    pub(crate) summary: Option<T>,
+
+    // fired when a committed transaction replaces the node, i.e. when its subtree changes
+    pub(crate) signal: Signal,
 }
 
-impl<T: NodeValue> Clone for Node<T> {
+impl<T: NodeValue, L: EdgeLabel> Default for Node<T, L> {
+    fn default() -> Self {
+        Self {
+            prefix: Prefix::default(),
+            leaf: None,
+            edges: Edges::default(),
+            summary: None,
+            signal: Signal::default(),
+        }
+    }
+}
+
+impl<T: NodeValue, L: EdgeLabel> Clone for Node<T, L> {
     fn clone(&self) -> Self {
         Self {
-            prefix: RwLock::new(self.prefix.read().clone()),
-            leaf: RwLock::new(self.leaf.read().clone()),
+            prefix: self.prefix.clone(),
+            leaf: self.leaf.clone(),
             edges: self.edges.clone(),
+            summary: self.summary.clone(),
+            // the copy is a new node, watched independently from the original
+            signal: Signal::default(),
         }
     }
+
+    fn clone_from(&mut self, source: &Self) {
+        self.prefix.clone_from(&source.prefix);
+        self.leaf.clone_from(&source.leaf);
+        self.edges.clone_from(&source.edges);
+        self.summary.clone_from(&source.summary);
+        self.signal = Signal::default();
+    }
 }
 
-impl<T: NodeValue> Hash for Node<T> {
+impl<T: NodeValue, L: EdgeLabel> Hash for Node<T, L> {
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
-        self.prefix.read().hash(state);
+        self.prefix.hash(state);
     }
 }
 
-impl<T: NodeValue> PartialEq for Node<T> {
+impl<T: NodeValue + PartialEq, L: EdgeLabel> PartialEq for Node<T, L> {
     fn eq(&self, other: &Self) -> bool {
-        if self.prefix.read().as_str() != other.prefix.read().as_str() {
-            return false;
-        }
-        if self.leaf.read().as_ref() != other.leaf.read().as_ref() {
-            return false;
+        // the subtrees are compared with an explicit stack, so deep trees cannot overflow
+        let mut stack = vec![(self, other)];
+        while let Some((node, other_node)) = stack.pop() {
+            if node.prefix != other_node.prefix
+                || node.leaf != other_node.leaf
+                || node.edge_len() != other_node.edge_len()
+            {
+                return false;
+            }
+            for (edge, other_edge) in node.edges().iter().zip(other_node.edges()) {
+                if edge.label != other_edge.label {
+                    return false;
+                }
+                stack.push((&edge.node, &other_edge.node));
+            }
         }
-        return self.edges == other.edges;
+        true
     }
 }
 
-impl<T: NodeValue> Eq for Node<T> {}
+impl<T: NodeValue + Eq, L: EdgeLabel> Eq for Node<T, L> {}
 
-impl<T: NodeValue> Node<T> {
+impl<T: NodeValue, L: EdgeLabel> Node<T, L> {
     /// Creates a new node with the given prefix and optional leaf node.
-    pub(crate) fn new(prefix: &str, leaf: Option<LeafNode<T>>) -> Self {
+    pub(crate) fn new(prefix: &[L], leaf: Option<LeafNode<T, L>>) -> Self {
         Self {
-            prefix: RwLock::new(prefix.to_string()),
-            leaf: RwLock::new(leaf.map(|l| Arc::new(l))),
+            prefix: prefix.into(),
+            leaf: leaf.map(Arc::new),
+            ..Default::default()
+        }
+    }
+
+    /// Creates a new node with the given prefix holding the given shared leaf node.
+    pub(crate) fn with_leaf(prefix: &[L], leaf: Arc<LeafNode<T, L>>) -> Self {
+        Self {
+            prefix: prefix.into(),
+            leaf: Some(leaf),
             ..Default::default()
         }
     }
 
     /// Creates a new node with the given prefix, optional leaf node, and edges.
     pub(crate) fn new_with_edges(
-        prefix: &str,
-        leaf: Option<LeafNode<T>>,
-        edges: Vec<Edge<T>>,
+        prefix: &[L],
+        leaf: Option<LeafNode<T, L>>,
+        edges: Vec<Edge<T, L>>,
     ) -> Self {
         let mut node = Self::new(prefix, leaf);
         node.edges = Edges::from(edges);
@@ -312,192 +547,331 @@ impl<T: NodeValue> Node<T> {
 
     /// Returns true if the node is a leaf node.
     pub(crate) fn is_leaf(&self) -> bool {
-        self.leaf.read().is_some()
+        self.leaf.is_some()
     }
 
     /// Returns the value of the leaf node if exists.
     pub(crate) fn get_value(&self) -> Option<T> {
-        match self.leaf.read().as_ref() {
-            Some(leaf_node) => Some(leaf_node.value.clone()),
-            None => None,
-        }
+        self.leaf.as_ref().map(|leaf_node| leaf_node.value.clone())
+    }
+
+    /// Reinitializes a node recycled from an arena with the given prefix and leaf node,
+    /// keeping the allocation of its edges.
+    pub(crate) fn recycle(&mut self, prefix: &[L], leaf: Option<Arc<LeafNode<T, L>>>) {
+        self.prefix = prefix.into();
+        self.leaf = leaf;
+        self.edges.clear();
+        self.summary = None;
+        self.signal = Signal::default();
+    }
+
+    /// Removes the leaf and edges of a node released to an arena, moving the nodes of the edges
+    /// into the given vector.
+    pub(crate) fn release_into(&mut self, nodes: &mut Vec<Arc<Node<T, L>>>) {
+        self.leaf = None;
+        self.summary = None;
+        self.edges.release_into(nodes);
     }
 
     /// Replaces the prefix of the node.
-    pub(crate) fn replace_prefix(&self, prefix: &str) {
-        let mut write_guard = self.prefix.write();
-        *write_guard = prefix.to_string();
+    pub(crate) fn replace_prefix(&mut self, prefix: &[L]) {
+        self.prefix = prefix.into();
     }
 
     /// Replaces the leaf node.
-    pub(crate) fn replace_leaf(&self, leaf: Option<LeafNode<T>>) {
-        let mut write_guard = self.leaf.write();
-        let leaf_node = leaf.map(|l| Arc::new(l));
-        *write_guard = leaf_node;
+    pub(crate) fn replace_leaf(&mut self, leaf: Option<LeafNode<T, L>>) {
+        self.leaf = leaf.map(Arc::new);
     }
 
     /// Adds an edge to the node.
-    pub(crate) fn add_edge(&self, edge: Edge<T>) {
-        self.edges.add_edge(edge);
+    pub(crate) fn add_edge(&mut self, edge: Edge<T, L>, collation: &dyn Collation<L>) {
+        self.edges.add_edge(edge, collation);
     }
 
     /// Replaces the node of the edge with the same label.
-    pub(crate) fn replace_edge(&self, edge: Edge<T>) {
-        self.edges.replace_edge(edge);
+    pub(crate) fn replace_edge(&mut self, edge: Edge<T, L>, collation: &dyn Collation<L>) {
+        self.edges.replace_edge(edge, collation);
     }
 
     /// Replaces the node of the edge at the given index.
-    pub(crate) fn replace_edge_at(&self, index: usize, edge: Edge<T>) {
+    pub(crate) fn replace_edge_at(&mut self, index: usize, edge: Edge<T, L>) {
         self.edges.replace_edge_at(index, edge);
     }
 
+    /// Returns the index and a reference to the node of the edge with the given label.
+    pub(crate) fn find_edge(
+        &self,
+        label: &L,
+        collation: &dyn Collation<L>,
+    ) -> Option<(usize, &Arc<Node<T, L>>)> {
+        self.edges.find_edge(label, collation)
+    }
+
     /// Returns the index and node of the edge with the given label.
-    pub(crate) fn get_edge(&self, label: u8) -> Option<(usize, Arc<Node<T>>)> {
-        self.edges.get_edge(label)
+    pub(crate) fn get_edge(
+        &self,
+        label: &L,
+        collation: &dyn Collation<L>,
+    ) -> Option<(usize, Arc<Node<T, L>>)> {
+        self.edges.get_edge(label, collation)
     }
 
     /// Returns the node of the edge at the given index.
-    pub(crate) fn get_edge_at(&self, index: usize) -> Option<Arc<Node<T>>> {
+    pub(crate) fn get_edge_at(&self, index: usize) -> Option<Arc<Node<T, L>>> {
         self.edges.get_edge_at(index)
     }
 
+    /// Returns a mutable reference to the node of the edge at the given index,
+    /// which is only writable in place while it is not shared.
+    pub(crate) fn edge_node_mut(&mut self, index: usize) -> &mut Arc<Node<T, L>> {
+        self.edges.edge_node_mut(index)
+    }
+
     /// Returns the index and node of the lowest edge with label >= given label.
-    pub(crate) fn get_lower_bound_edge(&self, label: u8) -> Option<(usize, Arc<Node<T>>)> {
-        self.edges.get_lower_bound_edge(label)
+    pub(crate) fn get_lower_bound_edge(
+        &self,
+        label: &L,
+        collation: &dyn Collation<L>,
+    ) -> Option<(usize, Arc<Node<T, L>>)> {
+        self.edges.get_lower_bound_edge(label, collation)
     }
 
     /// Deletes the edge with the given label.
-    pub(crate) fn delete_edge(&self, label: u8) {
-        self.edges.delete_edge(label);
+    pub(crate) fn delete_edge(&mut self, label: &L, collation: &dyn Collation<L>) {
+        self.edges.delete_edge(label, collation);
     }
 
-    /// Returns the value associated with the given key if exists.
-    pub(crate) fn get(&self, key: &str) -> Option<T> {
-        let mut search_bytes = key.as_bytes();
-        let mut current_node: Option<Arc<Node<T>>> = None;
+    /// Deletes the edge at the given index.
+    pub(crate) fn delete_edge_at(&mut self, index: usize) {
+        self.edges.delete_edge_at(index);
+    }
 
+    /// Returns the watch of the given (normalized) key and its leaf if exists. Without a leaf, the
+    /// watch of the deepest node on the path of the key is returned, which fires when it is inserted.
+    pub(crate) fn get_watch(
+        &self,
+        key: &[L],
+        collation: &dyn Collation<L>,
+    ) -> (Watch, Option<Arc<LeafNode<T, L>>>) {
+        let mut search = key;
+        let mut node = self;
         loop {
-            let node = match current_node.as_ref() {
-                Some(n) => n,
-                None => self,
-            };
-
-            if search_bytes.is_empty() {
-                if node.is_leaf() {
-                    let value = node.leaf.read().as_ref().unwrap().value.clone();
-                    return Some(value);
+            if search.is_empty() {
+                return match node.leaf.as_ref() {
+                    Some(leaf) => (leaf.signal.watch(), Some(leaf.clone())),
+                    None => (node.signal.watch(), None),
+                };
+            }
+            match node.find_edge(&search[0], collation) {
+                Some((_, child_node)) if search.starts_with(&child_node.prefix) => {
+                    search = &search[child_node.prefix.len()..];
+                    node = child_node;
                 }
-                break;
+                _ => return (node.signal.watch(), None),
             }
+        }
+    }
 
-            let node = match node.get_edge(search_bytes[0]) {
-                Some((_, n)) => {
-                    current_node.replace(n.clone());
-                    n
-                }
-                None => break,
+    /// Returns the watch of the deepest node covering every key with the given (normalized) prefix,
+    /// which fires when any key with the prefix changes.
+    pub(crate) fn watch_prefix(&self, prefix: &[L], collation: &dyn Collation<L>) -> Watch {
+        let mut search = prefix;
+        let mut node = self;
+        while !search.is_empty() {
+            let Some((_, child_node)) = node.find_edge(&search[0], collation) else {
+                break;
             };
-
-            if search_bytes.starts_with(node.prefix.read().as_str().as_bytes()) {
-                search_bytes = &search_bytes[node.prefix.read().len()..];
-            } else {
+            if child_node.prefix.starts_with(search) {
+                return child_node.signal.watch();
+            }
+            if !search.starts_with(&child_node.prefix) {
                 break;
             }
+            search = &search[child_node.prefix.len()..];
+            node = child_node;
+        }
+        node.signal.watch()
+    }
+
+    /// Returns the aggregate of the subtree.
+    pub(crate) fn get_summary(&self) -> Option<T> {
+        self.summary.clone()
+    }
+
+    /// Recomputes the aggregate of the subtree from the leaf and the aggregates of the children.
+    pub(crate) fn update_summary(&mut self, monoid: &dyn Monoid<T>) {
+        let mut summary = self.get_value();
+        self.edges.for_each(|edge| {
+            if let Some(child_summary) = edge.node.summary.as_ref() {
+                summary = Some(match summary.take() {
+                    Some(summary) => monoid.combine(&summary, child_summary),
+                    None => child_summary.clone(),
+                });
+            }
+        });
+        self.summary = summary;
+    }
+
+    /// Returns the node covering every key with the given (normalized) prefix, with the indexes
+    /// of the edges leading to it, if any key has the prefix.
+    pub(crate) fn find_prefix(
+        &self,
+        prefix: &[L],
+        collation: &dyn Collation<L>,
+    ) -> Option<(Vec<usize>, &Node<T, L>)> {
+        let mut path = Vec::new();
+        let mut search = prefix;
+        let mut node = self;
+        while !search.is_empty() {
+            let (edge_idx, child_node) = node.find_edge(&search[0], collation)?;
+            path.push(edge_idx);
+            if child_node.prefix.starts_with(search) {
+                return Some((path, child_node));
+            }
+            if !search.starts_with(&child_node.prefix) {
+                return None;
+            }
+            search = &search[child_node.prefix.len()..];
+            node = child_node;
         }
-        None
+        Some((path, node))
     }
 
-    /// Returns the key and value with the longest prefix match for the given key.
-    pub(crate) fn longest_prefix(&self, key: &str) -> Option<(String, T)> {
-        let mut last: Option<Arc<LeafNode<T>>> = None;
-        let mut search_bytes = key.as_bytes();
-        let mut current_node: Option<Arc<Node<T>>> = None;
+    /// Returns the aggregate of all the values whose (normalized) key starts with the given prefix.
+    pub(crate) fn aggregate_prefix(&self, prefix: &[L], collation: &dyn Collation<L>) -> Option<T> {
+        self.find_prefix(prefix, collation)?.1.get_summary()
+    }
+
+    /// Returns the value associated with the given (normalized) key if exists.
+    pub(crate) fn get(&self, key: &[L], collation: &dyn Collation<L>) -> Option<T> {
+        self.get_leaf(key, collation)
+            .map(|leaf_node| leaf_node.value.clone())
+    }
+
+    /// Returns the leaf node associated with the given (normalized) key if exists.
+    pub(crate) fn get_leaf(
+        &self,
+        key: &[L],
+        collation: &dyn Collation<L>,
+    ) -> Option<Arc<LeafNode<T, L>>> {
+        let mut search = key;
+        let mut node = self;
 
         loop {
-            let node = match current_node.as_ref() {
-                Some(n) => n,
-                None => self,
-            };
+            if search.is_empty() {
+                return node.leaf.clone();
+            }
+
+            let (_, child_node) = node.find_edge(&search[0], collation)?;
+            if !search.starts_with(&child_node.prefix) {
+                return None;
+            }
+            search = &search[child_node.prefix.len()..];
+            node = child_node;
+        }
+    }
+
+    /// Returns the key and value with the longest prefix match for the given (normalized) key.
+    pub(crate) fn longest_prefix(
+        &self,
+        key: &[L],
+        collation: &dyn Collation<L>,
+    ) -> Option<(Vec<L>, T)> {
+        let mut last: Option<&LeafNode<T, L>> = None;
+        let mut search = key;
+        let mut node = self;
 
-            if node.is_leaf() {
-                last.replace(node.leaf.read().as_ref().unwrap().clone());
+        loop {
+            if let Some(leaf) = node.leaf.as_ref() {
+                last.replace(leaf);
             }
 
-            if search_bytes.is_empty() {
+            if search.is_empty() {
                 break;
             }
 
-            let node = match node.get_edge(search_bytes[0]) {
-                Some((_, n)) => {
-                    current_node.replace(n.clone());
-                    n
-                }
-                None => break,
+            let Some((_, child_node)) = node.find_edge(&search[0], collation) else {
+                break;
             };
-
-            if search_bytes.starts_with(node.prefix.read().as_str().as_bytes()) {
-                search_bytes = &search_bytes[node.prefix.read().len()..];
-            } else {
+            if !search.starts_with(&child_node.prefix) {
                 break;
             }
+            search = &search[child_node.prefix.len()..];
+            node = child_node;
         }
 
-        match last {
-            // TODO: need to optimise to return &str instead of String
-            // consider using [FastStr]
-            Some(leaf) => Some((leaf.key.clone(), leaf.value.clone())),
-            None => None,
-        }
+        // TODO: need to optimise to return &[L] instead of Vec<L>
+        last.map(|leaf| (leaf.key.clone(), leaf.value.clone()))
     }
 
-    /// Returns the key and value with the minimum key in the subtree.
-    pub(crate) fn minimum(&self) -> Option<(String, T)> {
-        let mut current_node: Option<Arc<Node<T>>> = None;
+    /// Returns a copy of the subtree whose leaves are replaced by the ones returned by the given
+    /// function in key order, with the aggregates recomputed by the given monoid if any.
+    /// The subtree is copied with an explicit stack, so very deep trees do not overflow the stack.
+    pub(crate) fn rebuild<U: NodeValue>(
+        &self,
+        leaf_fn: &mut impl FnMut(&Arc<LeafNode<T, L>>) -> Arc<LeafNode<U, L>>,
+        monoid: Option<&dyn Monoid<U>>,
+    ) -> Node<U, L> {
+        // nodes being copied, with their new leaf and the copies of their children so far
+        let mut stack = vec![(self, self.leaf.as_ref().map(&mut *leaf_fn), Vec::new())];
         loop {
-            let node = match current_node.as_ref() {
-                Some(n) => n,
-                None => self,
-            };
-
-            if node.is_leaf() {
-                let leaf_node = node.leaf.read();
-                let leaf_node = leaf_node.as_ref().unwrap();
-                return Some((leaf_node.key.clone(), leaf_node.value.clone()));
+            let (node, _, edges) = stack
+                .last()
+                .expect("the stack holds the root until the end");
+            if let Some(edge) = node.edges.as_slice().get(edges.len()) {
+                let child_node = edge.get_node();
+                let leaf = child_node.leaf.as_ref().map(&mut *leaf_fn);
+                stack.push((child_node, leaf, Vec::new()));
+                continue;
             }
 
-            match node.edges.first() {
-                Some(first_edge_node) => {
-                    current_node.replace(first_edge_node);
-                }
-                None => break,
+            let (node, leaf, edges) = stack.pop().expect("the stack holds the current node");
+            let mut copy = Node {
+                prefix: node.prefix.clone(),
+                leaf,
+                edges: Edges::from(edges),
+                summary: None,
+                signal: Signal::default(),
+            };
+            if let Some(monoid) = monoid {
+                copy.update_summary(monoid);
             }
+            let Some((parent, _, parent_edges)) = stack.last_mut() else {
+                return copy;
+            };
+            let label = parent.edges.as_slice()[parent_edges.len()].label.clone();
+            parent_edges.push(Edge::new(label, Arc::new(copy)));
         }
-        None
     }
 
-    /// Returns the key and value with the maximum key in the subtree.
-    pub(crate) fn maximum(&self) -> Option<(String, T)> {
-        let mut current_node: Option<Arc<Node<T>>> = None;
-        loop {
-            let node = match current_node.as_ref() {
-                Some(n) => n,
-                None => self,
-            };
-
-            if let Some(last_edge_node) = node.edges.last() {
-                current_node.replace(last_edge_node);
-                continue;
-            }
+    /// Returns a copy of the subtree with every value mapped by the given function in key order.
+    pub(crate) fn map<U: NodeValue>(&self, f: &mut impl FnMut(&T) -> U) -> Node<U, L> {
+        self.rebuild(
+            &mut |leaf| Arc::new(LeafNode::new(&leaf.key, f(&leaf.value))),
+            None,
+        )
+    }
 
-            if node.is_leaf() {
-                let leaf_node = node.leaf.read();
-                let leaf_node = leaf_node.as_ref().unwrap();
+    /// Returns the key and value with the minimum key in the subtree.
+    pub(crate) fn minimum(&self) -> Option<(Vec<L>, T)> {
+        let mut node = self;
+        loop {
+            if let Some(leaf_node) = node.leaf.as_ref() {
                 return Some((leaf_node.key.clone(), leaf_node.value.clone()));
-            } else {
-                break;
             }
+            node = &node.edges.as_slice().first()?.node;
         }
-        None
+    }
+
+    /// Returns the key and value with the maximum key in the subtree.
+    pub(crate) fn maximum(&self) -> Option<(Vec<L>, T)> {
+        let mut node = self;
+        while let Some(last_edge) = node.edges.as_slice().last() {
+            node = &last_edge.node;
+        }
+        node.leaf
+            .as_ref()
+            .map(|leaf_node| (leaf_node.key.clone(), leaf_node.value.clone()))
     }
 
     /// Returns true if there are no edges.
@@ -511,70 +885,100 @@ impl<T: NodeValue> Node<T> {
     }
 
     /// Returns the first edge's node if exists.
-    pub(crate) fn first_edge(&self) -> Option<Arc<Node<T>>> {
+    pub(crate) fn first_edge(&self) -> Option<Arc<Node<T, L>>> {
         self.edges.first()
     }
 
     /// Returns the last edge's node if exists.
-    pub(crate) fn last_edge(&self) -> Option<Arc<Node<T>>> {
+    pub(crate) fn last_edge(&self) -> Option<Arc<Node<T, L>>> {
         self.edges.last()
     }
 
+    /// Returns the edges of the node in order.
+    pub(crate) fn edges(&self) -> &[Edge<T, L>] {
+        self.edges.as_slice()
+    }
+
     /// Clears all edges.
-    pub(crate) fn clear_edges(&self) {
+    pub(crate) fn clear_edges(&mut self) {
         self.edges.clear();
     }
 
     /// Resets all edges and clears capacity.
-    pub(crate) fn reset_edges(&self) {
+    pub(crate) fn reset_edges(&mut self) {
         self.edges.reset();
     }
 
     /// Removes and returns the last edge.
-    pub(crate) fn pop_edge(&self) -> Option<Edge<T>> {
+    pub(crate) fn pop_edge(&mut self) -> Option<Edge<T, L>> {
         self.edges.pop()
     }
 
-    /// Collects all edges from self and inserts them into other.
-    pub(crate) fn collect_into_edges(&self, edges: &Edges<T>) {
+    /// Copies all edges from self into other, leaving self untouched.
+    pub(crate) fn collect_into_edges(&self, edges: &mut Edges<T, L>) {
         self.edges.collect_into(edges)
     }
 
     /// Iterates over each edge and applies the given function.
     pub(crate) fn for_each_edge<F>(&self, f: F)
     where
-        F: FnMut(&Edge<T>),
+        F: FnMut(&Edge<T, L>),
     {
         self.edges.for_each(f);
     }
 }
 
 /// A leaf node represents the end of a key in the radix tree and holds the associated value.
-#[derive(Debug, Default, Clone, Hash, PartialEq, Eq)]
-pub struct LeafNode<T>
+#[derive(Debug, Default, Clone)]
+pub struct LeafNode<T, L = u8>
 where
     T: NodeValue,
+    L: EdgeLabel,
 {
-    key: String,
+    key: Vec<L>,
     value: T,
+
+    // fired when a committed transaction updates or deletes the key
+    signal: Signal,
 }
 
-impl<T: NodeValue> LeafNode<T> {
+impl<T: NodeValue + Hash, L: EdgeLabel> Hash for LeafNode<T, L> {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.key.hash(state);
+        self.value.hash(state);
+    }
+}
+
+impl<T: NodeValue + PartialEq, L: EdgeLabel> PartialEq for LeafNode<T, L> {
+    fn eq(&self, other: &Self) -> bool {
+        self.key == other.key && self.value == other.value
+    }
+}
+
+impl<T: NodeValue + Eq, L: EdgeLabel> Eq for LeafNode<T, L> {}
+
+impl<T: NodeValue, L: EdgeLabel> LeafNode<T, L> {
     /// Creates a new leaf node with the given key and value.
-    pub(crate) fn new(key: &str, value: T) -> Self {
+    pub(crate) fn new(key: &[L], value: T) -> Self {
         Self {
-            key: key.to_string(),
+            key: key.to_vec(),
             value,
+            signal: Signal::default(),
         }
     }
 
-    /// Returns the key of the leaf node.
-    pub(crate) fn get_key(&self) -> &str {
+    /// Returns the key of the leaf node, as it was inserted.
+    pub fn get_key(&self) -> &[L] {
         &self.key
     }
 
     /// Returns the value of the leaf node.
-    pub(crate) fn get_value(&self) -> &T {
+    pub fn get_value(&self) -> &T {
         &self.value
     }
+
+    /// Returns the signal fired when the key is updated or deleted.
+    pub(crate) fn signal(&self) -> &Signal {
+        &self.signal
+    }
 }
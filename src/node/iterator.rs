@@ -1,46 +1,188 @@
-use std::{collections::VecDeque, rc::Rc};
+use std::{cmp::Ordering, ops::Bound, sync::Arc};
 
-use crate::node::{Edge, Node};
+use crate::{
+    collation::Collation,
+    node::{LeafNode, Node},
+    utils::{EdgeLabel, NodeValue},
+};
 
-pub(crate) struct NodeIterator<T>
+/// Iterates over the leaves of a tree in key order.
+///
+/// The leaves are shared with the tree, so iterating never clones the keys or values.
+pub struct Iter<T, L = u8>
 where
-    T: Default + Clone,
+    T: NodeValue,
+    L: EdgeLabel,
 {
-    pub(crate) node: Option<Rc<Node<T>>>,
-    pub(crate) stack: Vec<VecDeque<Edge<T>>>,
+    // nodes left to visit, the last node is visited first
+    stack: Vec<Arc<Node<T, L>>>,
+
+    // collation used to order the edges of the tree
+    collation: Arc<dyn Collation<L>>,
 }
 
-impl<T: Clone + Default> Iterator for NodeIterator<T> {
-    type Item = (&'static str, T);
+impl<T: NodeValue, L: EdgeLabel> Iter<T, L> {
+    /// Creates an iterator over all the leaves of the given node.
+    pub(crate) fn new(node: Arc<Node<T, L>>, collation: Arc<dyn Collation<L>>) -> Self {
+        Self {
+            stack: vec![node],
+            collation,
+        }
+    }
 
-    fn next(&mut self) -> Option<Self::Item> {
-        if self.stack.is_empty() && self.node.is_some() {
-            self.stack.push(VecDeque::from([Edge {
-                node: self.node.as_ref().unwrap().clone(),
-                ..Default::default()
-            }]));
+    /// Restricts the iterator to the keys starting with the given (normalized) prefix.
+    pub(crate) fn seek_prefix(&mut self, prefix: &[L]) {
+        let Some(mut node) = self.stack.pop() else {
+            return;
+        };
+        self.stack.clear();
+
+        let mut search = prefix;
+        loop {
+            if search.is_empty() {
+                self.stack.push(node);
+                return;
+            }
+
+            let child_node = match node.get_edge(&search[0], self.collation.as_ref()) {
+                Some((_, child_node)) => child_node,
+                None => return,
+            };
+
+            if search.starts_with(&child_node.prefix) {
+                search = &search[child_node.prefix.len()..];
+                node = child_node;
+            } else if child_node.prefix.starts_with(search) {
+                self.stack.push(child_node);
+                return;
+            } else {
+                return;
+            }
         }
+    }
 
-        while !self.stack.is_empty() {
-            let last_edges = self.stack.last_mut().unwrap();
-            let elem = last_edges.pop_front().unwrap().node;
+    /// Moves the iterator to the smallest key greater than or equal to the given (normalized) key.
+    pub(crate) fn seek_lower_bound(&mut self, key: &[L]) {
+        let Some(mut node) = self.stack.pop() else {
+            return;
+        };
+        self.stack.clear();
 
-            // note: remove from stack if the edges are empty
-            if last_edges.is_empty() {
-                self.stack.pop();
+        let mut search = key;
+        loop {
+            let len = node.prefix.len().min(search.len());
+            let prefix_cmp = self.collation.compare_keys(&node.prefix, &search[..len]);
+
+            match prefix_cmp {
+                // every key in the subtree is greater than the search key
+                Ordering::Greater => {
+                    self.stack.push(node);
+                    return;
+                }
+                // every key in the subtree is less than the search key
+                Ordering::Less => return,
+                Ordering::Equal => {}
             }
 
-            if !elem.edges.is_empty() {
-                self.stack.push(VecDeque::from_iter(elem.edges.clone()));
+            let prefix_len = node.prefix.len();
+            if prefix_len >= search.len() {
+                // the search key is a prefix of every key in the subtree
+                self.stack.push(node);
+                return;
             }
+            search = &search[prefix_len..];
+
+            let (edge_idx, child_node) =
+                match node.get_lower_bound_edge(&search[0], self.collation.as_ref()) {
+                    Some(edge) => edge,
+                    None => return,
+                };
+
+            // the leaf of the node is skipped as it is shorter than the search key,
+            // visit the greater siblings after the lower bound child
+            self.stack.extend(
+                node.edges()[edge_idx + 1..]
+                    .iter()
+                    .rev()
+                    .map(|e| e.node.clone()),
+            );
+
+            node = child_node;
+        }
+    }
+}
+
+impl<T: NodeValue, L: EdgeLabel> Iterator for Iter<T, L> {
+    type Item = Arc<LeafNode<T, L>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(node) = self.stack.pop() {
+            // push the children in reverse order so the smallest child is visited first
+            self.stack
+                .extend(node.edges().iter().rev().map(|e| e.node.clone()));
 
-            if elem.leaf.is_some() {
-                return Some((
-                    elem.leaf.as_ref().unwrap().key,
-                    elem.leaf.as_ref().unwrap().value.clone(),
-                ));
+            if let Some(leaf) = node.leaf.as_ref() {
+                return Some(leaf.clone());
             }
         }
         None
     }
 }
+
+/// Iterates over the leaves of a tree whose keys are within a range, in key order.
+pub struct Range<T, L = u8>
+where
+    T: NodeValue,
+    L: EdgeLabel,
+{
+    iter: Iter<T, L>,
+
+    // normalized bounds of the range
+    start: Bound<Vec<L>>,
+    end: Bound<Vec<L>>,
+}
+
+impl<T: NodeValue, L: EdgeLabel> Range<T, L> {
+    /// Creates an iterator over the leaves of the given node within the (normalized) bounds.
+    pub(crate) fn new(
+        node: Arc<Node<T, L>>,
+        collation: Arc<dyn Collation<L>>,
+        start: Bound<Vec<L>>,
+        end: Bound<Vec<L>>,
+    ) -> Self {
+        let mut iter = Iter::new(node, collation);
+        if let Bound::Included(key) | Bound::Excluded(key) = &start {
+            iter.seek_lower_bound(key);
+        }
+        Self { iter, start, end }
+    }
+}
+
+impl<T: NodeValue, L: EdgeLabel> Iterator for Range<T, L> {
+    type Item = Arc<LeafNode<T, L>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let leaf = self.iter.next()?;
+            let collation = self.iter.collation.as_ref();
+            let key = collation.normalize(leaf.get_key());
+
+            if let Bound::Excluded(start) = &self.start
+                && collation.compare_keys(&key, start).is_eq()
+            {
+                continue;
+            }
+
+            let in_range = match &self.end {
+                Bound::Included(end) => collation.compare_keys(&key, end).is_le(),
+                Bound::Excluded(end) => collation.compare_keys(&key, end).is_lt(),
+                Bound::Unbounded => true,
+            };
+            if !in_range {
+                self.iter.stack.clear();
+                return None;
+            }
+            return Some(leaf);
+        }
+    }
+}
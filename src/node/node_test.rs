@@ -1,10 +1,12 @@
 #[cfg(test)]
 mod tests {
-    use std::sync::Arc;
+    use std::{cmp::Ordering, sync::Arc};
 
-    use parking_lot::lock_api::RwLock;
-
-    use crate::node::{Edge, LeafNode, Node};
+    use crate::{
+        collation::{Binary, Collation},
+        node::{Edge, EdgeIndex, LeafNode, Node},
+        watch::Signal,
+    };
 
     #[derive(Default, Debug, Clone, Hash, PartialEq, Eq)]
     struct TestValue {
@@ -14,27 +16,33 @@ mod tests {
     #[test]
     fn test_node_clone() {
         let original_node: Node<TestValue> = Node {
-            prefix: RwLock::new("prefix".into()),
-            leaf: RwLock::new(Some(Arc::new(LeafNode {
+            prefix: "prefix".into(),
+            summary: None,
+            signal: Signal::default(),
+            leaf: Some(Arc::new(LeafNode {
                 value: TestValue {
                     data: "value".into(),
                 },
                 key: "key".into(),
-            }))),
+                signal: Signal::default(),
+            })),
             edges: vec![Edge {
                 label: b'a',
                 node: Arc::new(Node {
-                    prefix: RwLock::new("a".into()),
-                    leaf: RwLock::new(Some(Arc::new(LeafNode {
+                    prefix: "a".into(),
+                    summary: None,
+                    signal: Signal::default(),
+                    leaf: Some(Arc::new(LeafNode {
                         value: TestValue {
                             data: "a_value".into(),
                         },
                         key: "a_key".into(),
-                    }))),
+                        signal: Signal::default(),
+                    })),
                     edges: vec![Edge {
                         label: b'b',
                         node: Arc::new(Node {
-                            prefix: RwLock::new("ab".into()),
+                            prefix: "ab".into(),
                             ..Default::default()
                         }),
                     }]
@@ -42,62 +50,73 @@ mod tests {
                 }),
             }]
             .into(),
-        }
-        .into();
+        };
         let original_child_node = original_node.first_edge().unwrap();
 
-        let cloned_node = original_node.clone();
+        let mut cloned_node = original_node.clone();
         let cloned_child_node = cloned_node.first_edge().unwrap();
+        assert!(
+            Arc::ptr_eq(&original_child_node, &cloned_child_node),
+            "child nodes should be shared between clones"
+        );
+
         {
-            cloned_child_node.reset_edges();
-            let mut write_guard = cloned_child_node.leaf.write();
-            write_guard.take();
+            cloned_node.reset_edges();
+            cloned_node.leaf.take();
         }
 
+        assert!(!cloned_node.is_leaf(), "cloned node should have no leaf");
         assert!(
-            !cloned_child_node.is_leaf(),
-            "child node should have no leaf"
-        );
-        assert!(
-            original_child_node.is_leaf(),
-            "original child node should remain unchanged"
+            original_node.is_leaf(),
+            "original node should remain unchanged"
         );
         assert_eq!(
-            cloned_child_node.edge_len(),
+            cloned_node.edge_len(),
             0,
-            "child node should have no edges"
+            "cloned node should have no edges"
+        );
+        assert_eq!(
+            original_node.edge_len(),
+            1,
+            "original node should remain unchanged"
         );
         assert_eq!(
             original_child_node.edge_len(),
             1,
-            "original child node should remain unchanged"
+            "shared child node should remain unchanged"
         );
     }
 
     #[test]
     fn test_node_equality() {
         let base_node: Node<TestValue> = Node {
-            prefix: RwLock::new("prefix".into()),
-            leaf: RwLock::new(Some(Arc::new(LeafNode {
+            prefix: "prefix".into(),
+            summary: None,
+            signal: Signal::default(),
+            leaf: Some(Arc::new(LeafNode {
                 value: TestValue {
                     data: "value".into(),
                 },
                 key: "key".into(),
-            }))),
+                signal: Signal::default(),
+            })),
             edges: vec![Edge {
                 label: b'a',
                 node: Arc::new(Node {
-                    prefix: RwLock::new("a".into()),
-                    leaf: RwLock::new(Some(Arc::new(LeafNode {
+                    prefix: "a".into(),
+                    summary: None,
+                    signal: Signal::default(),
+                    leaf: Some(Arc::new(LeafNode {
                         value: TestValue {
                             data: "a_value".into(),
                         },
                         key: "a_key".into(),
-                    }))),
+                        signal: Signal::default(),
+                    })),
                     edges: vec![Edge {
                         label: b'b',
                         node: Arc::new(Node {
-                            prefix: RwLock::new("ab".into()),
+                            prefix: "ab".into(),
                             ..Default::default()
                         }),
                     }]
@@ -109,27 +128,33 @@ mod tests {
 
         {
             let node_eq: Node<TestValue> = Node {
-                prefix: RwLock::new("prefix".into()),
-                leaf: RwLock::new(Some(Arc::new(LeafNode {
+                prefix: "prefix".into(),
+                summary: None,
+                signal: Signal::default(),
+                leaf: Some(Arc::new(LeafNode {
                     value: TestValue {
                         data: "value".into(),
                     },
                     key: "key".into(),
-                }))),
+                    signal: Signal::default(),
+                })),
                 edges: vec![Edge {
                     label: b'a',
                     node: Arc::new(Node {
-                        prefix: RwLock::new("a".into()),
-                        leaf: RwLock::new(Some(Arc::new(LeafNode {
+                        prefix: "a".into(),
+                        summary: None,
+                        signal: Signal::default(),
+                        leaf: Some(Arc::new(LeafNode {
                             value: TestValue {
                                 data: "a_value".into(),
                             },
                             key: "a_key".into(),
-                        }))),
+                            signal: Signal::default(),
+                        })),
                         edges: vec![Edge {
                             label: b'b',
                             node: Arc::new(Node {
-                                prefix: RwLock::new("ab".into()),
+                                prefix: "ab".into(),
                                 ..Default::default()
                             }),
                         }]
@@ -146,10 +171,9 @@ mod tests {
         }
 
         {
-            let node_prefix_diff = base_node.clone();
+            let mut node_prefix_diff = base_node.clone();
             {
-                let mut write_guard = node_prefix_diff.prefix.write();
-                *write_guard = "diff".into();
+                node_prefix_diff.prefix = "diff".into();
             }
 
             assert_ne!(
@@ -159,15 +183,15 @@ mod tests {
         }
 
         {
-            let node_leaf_diff_key = base_node.clone();
+            let mut node_leaf_diff_key = base_node.clone();
 
             {
-                let mut write_guard = node_leaf_diff_key.leaf.write();
-                *write_guard = Some(Arc::new(LeafNode {
+                node_leaf_diff_key.leaf = Some(Arc::new(LeafNode {
                     value: TestValue {
                         data: "value".into(),
                     },
                     key: "key1".into(),
+                    signal: Signal::default(),
                 }));
             }
 
@@ -178,14 +202,14 @@ mod tests {
         }
 
         {
-            let node_leaf_diff_value = base_node.clone();
+            let mut node_leaf_diff_value = base_node.clone();
             {
-                let mut write_guard = node_leaf_diff_value.leaf.write();
-                *write_guard = Some(Arc::new(LeafNode {
+                node_leaf_diff_value.leaf = Some(Arc::new(LeafNode {
                     value: TestValue {
                         data: "value1".into(),
                     },
                     key: "key".into(),
+                    signal: Signal::default(),
                 }));
             }
 
@@ -196,10 +220,9 @@ mod tests {
         }
 
         {
-            let node_missing_leaf = base_node.clone();
+            let mut node_missing_leaf = base_node.clone();
             {
-                let mut write_guard = node_missing_leaf.leaf.write();
-                *write_guard = None;
+                node_missing_leaf.leaf = None;
             }
 
             assert_ne!(
@@ -209,13 +232,13 @@ mod tests {
         }
 
         {
-            let node_with_different_edge_node_prefix = base_node.clone();
+            let mut node_with_different_edge_node_prefix = base_node.clone();
             {
-                let mut write_guard = node_with_different_edge_node_prefix.edges.0.write();
-                write_guard[0] = Edge {
+                let edges = &mut node_with_different_edge_node_prefix.edges.0;
+                edges[0] = Edge {
                     label: b'a',
                     node: Arc::new(Node {
-                        prefix: RwLock::new("different".into()),
+                        prefix: "different".into(),
                         ..Default::default()
                     }),
                 };
@@ -228,12 +251,12 @@ mod tests {
         }
 
         {
-            let node_with_different_edge_label = base_node.clone();
+            let mut node_with_different_edge_label = base_node.clone();
             {
-                let mut write_guard = node_with_different_edge_label.edges.0.write();
-                write_guard[0] = Edge {
+                let edges = &mut node_with_different_edge_label.edges.0;
+                edges[0] = Edge {
                     label: b'b',
-                    node: write_guard[0].node.clone(),
+                    node: edges[0].node.clone(),
                 };
             }
 
@@ -244,10 +267,10 @@ mod tests {
         }
 
         {
-            let node_with_missing_edge = base_node.clone();
+            let mut node_with_missing_edge = base_node.clone();
             {
-                let mut write_guard = node_with_missing_edge.edges.0.write();
-                write_guard.clear();
+                let edges = &mut node_with_missing_edge.edges.0;
+                edges.clear();
             }
 
             assert_ne!(
@@ -257,13 +280,13 @@ mod tests {
         }
 
         {
-            let node_with_additional_edge = base_node.clone();
+            let mut node_with_additional_edge = base_node.clone();
             {
-                let mut write_guard = node_with_additional_edge.edges.0.write();
-                write_guard.push(Edge {
+                let edges = &mut node_with_additional_edge.edges.0;
+                edges.push(Edge {
                     label: b'c',
                     node: Arc::new(Node {
-                        prefix: RwLock::new("c".into()),
+                        prefix: "c".into(),
                         ..Default::default()
                     }),
                 });
@@ -275,24 +298,27 @@ mod tests {
         }
 
         {
-            let node_with_different_edge_node_prefix = base_node.clone();
+            let mut node_with_different_edge_node_prefix = base_node.clone();
             {
-                let mut write_guard = node_with_different_edge_node_prefix.edges.0.write();
-                let edge = &mut write_guard[0];
+                let edges = &mut node_with_different_edge_node_prefix.edges.0;
+                let edge = &mut edges[0];
                 *edge = Edge {
                     label: b'a',
                     node: Arc::new(Node {
-                        prefix: RwLock::new("different".into()),
-                        leaf: RwLock::new(Some(Arc::new(LeafNode {
+                        prefix: "different".into(),
+                        summary: None,
+                        signal: Signal::default(),
+                        leaf: Some(Arc::new(LeafNode {
                             value: TestValue {
                                 data: "a_value".into(),
                             },
                             key: "a_key".into(),
-                        }))),
+                            signal: Signal::default(),
+                        })),
                         edges: vec![Edge {
                             label: b'b',
                             node: Arc::new(Node {
-                                prefix: RwLock::new("ab".into()),
+                                prefix: "ab".into(),
                                 ..Default::default()
                             }),
                         }]
@@ -308,24 +334,27 @@ mod tests {
         }
 
         {
-            let node_with_different_edge_node_leaf_key = base_node.clone();
+            let mut node_with_different_edge_node_leaf_key = base_node.clone();
             {
-                let mut write_guard = node_with_different_edge_node_leaf_key.edges.0.write();
-                let edge = &mut write_guard[0];
+                let edges = &mut node_with_different_edge_node_leaf_key.edges.0;
+                let edge = &mut edges[0];
                 *edge = Edge {
                     label: b'a',
                     node: Arc::new(Node {
-                        prefix: RwLock::new("a".into()),
-                        leaf: RwLock::new(Some(Arc::new(LeafNode {
+                        prefix: "a".into(),
+                        summary: None,
+                        signal: Signal::default(),
+                        leaf: Some(Arc::new(LeafNode {
                             value: TestValue {
                                 data: "a_value".into(),
                             },
                             key: "different_key".into(),
-                        }))),
+                            signal: Signal::default(),
+                        })),
                         edges: vec![Edge {
                             label: b'b',
                             node: Arc::new(Node {
-                                prefix: RwLock::new("ab".into()),
+                                prefix: "ab".into(),
                                 ..Default::default()
                             }),
                         }]
@@ -341,24 +370,27 @@ mod tests {
         }
 
         {
-            let node_with_different_edge_node_leaf_value = base_node.clone();
+            let mut node_with_different_edge_node_leaf_value = base_node.clone();
             {
-                let mut write_guard = node_with_different_edge_node_leaf_value.edges.0.write();
-                let edge = &mut write_guard[0];
+                let edges = &mut node_with_different_edge_node_leaf_value.edges.0;
+                let edge = &mut edges[0];
                 *edge = Edge {
                     label: b'a',
                     node: Arc::new(Node {
-                        prefix: RwLock::new("a".into()),
-                        leaf: RwLock::new(Some(Arc::new(LeafNode {
+                        prefix: "a".into(),
+                        summary: None,
+                        signal: Signal::default(),
+                        leaf: Some(Arc::new(LeafNode {
                             value: TestValue {
                                 data: "different".into(),
                             },
                             key: "a_key".into(),
-                        }))),
+                            signal: Signal::default(),
+                        })),
                         edges: vec![Edge {
                             label: b'b',
                             node: Arc::new(Node {
-                                prefix: RwLock::new("ab".into()),
+                                prefix: "ab".into(),
                                 ..Default::default()
                             }),
                         }]
@@ -374,19 +406,21 @@ mod tests {
         }
 
         {
-            let node_with_missing_edge_node_leaf = base_node.clone();
+            let mut node_with_missing_edge_node_leaf = base_node.clone();
             {
-                let mut write_guard = node_with_missing_edge_node_leaf.edges.0.write();
-                let edge = &mut write_guard[0];
+                let edges = &mut node_with_missing_edge_node_leaf.edges.0;
+                let edge = &mut edges[0];
                 *edge = Edge {
                     label: b'a',
                     node: Arc::new(Node {
-                        prefix: RwLock::new("a".into()),
-                        leaf: RwLock::new(None),
+                        prefix: "a".into(),
+                        summary: None,
+                        signal: Signal::default(),
+                        leaf: None,
                         edges: vec![Edge {
                             label: b'b',
                             node: Arc::new(Node {
-                                prefix: RwLock::new("ab".into()),
+                                prefix: "ab".into(),
                                 ..Default::default()
                             }),
                         }]
@@ -402,32 +436,35 @@ mod tests {
         }
 
         {
-            let node_with_additional_edge_node_edge = base_node.clone();
+            let mut node_with_additional_edge_node_edge = base_node.clone();
             {
-                let mut write_guard = node_with_additional_edge_node_edge.edges.0.write();
-                let edge = &mut write_guard[0];
+                let edges = &mut node_with_additional_edge_node_edge.edges.0;
+                let edge = &mut edges[0];
                 *edge = Edge {
                     label: b'a',
                     node: Arc::new(Node {
-                        prefix: RwLock::new("a".into()),
-                        leaf: RwLock::new(Some(Arc::new(LeafNode {
+                        prefix: "a".into(),
+                        summary: None,
+                        signal: Signal::default(),
+                        leaf: Some(Arc::new(LeafNode {
                             value: TestValue {
                                 data: "a_value".into(),
                             },
                             key: "a_key".into(),
-                        }))),
+                            signal: Signal::default(),
+                        })),
                         edges: vec![
                             Edge {
                                 label: b'b',
                                 node: Arc::new(Node {
-                                    prefix: RwLock::new("ab".into()),
+                                    prefix: "ab".into(),
                                     ..Default::default()
                                 }),
                             },
                             Edge {
                                 label: b'c',
                                 node: Arc::new(Node {
-                                    prefix: RwLock::new("ac".into()),
+                                    prefix: "ac".into(),
                                     ..Default::default()
                                 }),
                             },
@@ -444,20 +481,23 @@ mod tests {
         }
 
         {
-            let node_with_missing_edge_node_edge = base_node.clone();
+            let mut node_with_missing_edge_node_edge = base_node.clone();
             {
-                let mut write_guard = node_with_missing_edge_node_edge.edges.0.write();
-                let edge = &mut write_guard[0];
+                let edges = &mut node_with_missing_edge_node_edge.edges.0;
+                let edge = &mut edges[0];
                 *edge = Edge {
                     label: b'a',
                     node: Arc::new(Node {
-                        prefix: RwLock::new("a".into()),
-                        leaf: RwLock::new(Some(Arc::new(LeafNode {
+                        prefix: "a".into(),
+                        summary: None,
+                        signal: Signal::default(),
+                        leaf: Some(Arc::new(LeafNode {
                             value: TestValue {
                                 data: "a_value".into(),
                             },
                             key: "a_key".into(),
-                        }))),
+                            signal: Signal::default(),
+                        })),
                         edges: vec![].into(),
                     }),
                 };
@@ -473,27 +513,28 @@ mod tests {
                     data: "test".into(),
                 },
                 key: "key".into(),
+                signal: Signal::default(),
             };
 
-            let node = Node::new("prefix", Some(leaf_node.clone()));
-            assert_eq!(node.prefix.read().as_str(), "prefix");
-            assert!(node.leaf.read().is_some());
-            let stored_leaf = node.leaf.read();
+            let node = Node::new(b"prefix", Some(leaf_node.clone()));
+            assert_eq!(node.prefix.as_slice(), b"prefix");
+            assert!(node.leaf.is_some());
+            let stored_leaf = &node.leaf;
             let stored_leaf = stored_leaf.as_ref().unwrap();
             assert_eq!(stored_leaf.key, leaf_node.key);
             assert_eq!(stored_leaf.value, leaf_node.value);
         }
 
         {
-            let node_no_leaf: Node<TestValue> = Node::new("no_leaf", None);
-            assert_eq!(node_no_leaf.prefix.read().as_str(), "no_leaf");
-            assert!(node_no_leaf.leaf.read().is_none());
+            let node_no_leaf: Node<TestValue> = Node::new(b"no_leaf", None);
+            assert_eq!(node_no_leaf.prefix.as_slice(), b"no_leaf");
+            assert!(node_no_leaf.leaf.is_none());
         }
 
         {
-            let blank_node: Node<TestValue> = Node::new("", None);
-            assert_eq!(blank_node.prefix.read().as_str(), "");
-            assert!(blank_node.leaf.read().is_none());
+            let blank_node: Node<TestValue> = Node::new(b"", None);
+            assert_eq!(blank_node.prefix.as_slice(), b"");
+            assert!(blank_node.leaf.is_none());
         }
     }
 
@@ -504,10 +545,11 @@ mod tests {
                 data: "test".into(),
             },
             key: "key".into(),
+            signal: Signal::default(),
         };
 
         let node = Node {
-            leaf: RwLock::new(Some(Arc::new(leaf))),
+            leaf: Some(Arc::new(leaf)),
             ..Default::default()
         };
         assert!(node.is_leaf(), "should return true for leaf node");
@@ -518,19 +560,19 @@ mod tests {
 
     #[test]
     fn test_replace_prefix() {
-        let node: Node<TestValue> = Node::new("old_prefix", None);
-        assert_eq!(node.prefix.read().as_str(), "old_prefix");
+        let mut node: Node<TestValue> = Node::new(b"old_prefix", None);
+        assert_eq!(node.prefix.as_slice(), b"old_prefix");
 
-        node.replace_prefix("new_prefix");
-        assert_eq!(node.prefix.read().as_str(), "new_prefix");
+        node.replace_prefix(b"new_prefix");
+        assert_eq!(node.prefix.as_slice(), b"new_prefix");
 
-        node.replace_prefix("");
-        assert_eq!(node.prefix.read().as_str(), "");
+        node.replace_prefix(b"");
+        assert_eq!(node.prefix.as_slice(), b"");
     }
 
     #[test]
     fn test_replace_leaf() {
-        let node: Node<TestValue> = Node::default();
+        let mut node: Node<TestValue> = Node::default();
 
         // replace with a new leaf
         let new_leaf = LeafNode {
@@ -538,11 +580,12 @@ mod tests {
                 data: "new_data".into(),
             },
             key: "new_key".into(),
+            signal: Signal::default(),
         };
         node.replace_leaf(Some(new_leaf.clone()));
 
         {
-            let stored_leaf = node.leaf.read();
+            let stored_leaf = &node.leaf;
             assert!(
                 stored_leaf.is_some(),
                 "leaf should be present after replacement"
@@ -556,7 +599,7 @@ mod tests {
         node.replace_leaf(None);
 
         {
-            let stored_leaf = node.leaf.read();
+            let stored_leaf = &node.leaf;
             assert!(stored_leaf.is_none(), "leaf should be None after removal");
         }
     }
@@ -565,22 +608,22 @@ mod tests {
     fn test_add_edge() {
         {
             // insert into Node with no edges
-            let node: Node<TestValue> = Node::default();
+            let mut node: Node<TestValue> = Node::default();
             let edge: Edge<TestValue> = Edge {
                 label: b'a',
                 node: Node::default().into(),
             };
 
-            node.add_edge(edge.clone());
+            node.add_edge(edge.clone(), &Binary);
 
-            let edges = node.edges.0.read();
+            let edges = &node.edges.0;
             assert_eq!(edges.len(), 1);
             assert_eq!(edges[0], edge);
         }
 
         {
             // insert into Node with existing edges
-            let node: Node<TestValue> = Node::default();
+            let mut node: Node<TestValue> = Node::default();
             let edge_b = Edge {
                 label: b'b',
                 node: Node::default().into(),
@@ -589,17 +632,17 @@ mod tests {
                 label: b'd',
                 node: Node::default().into(),
             };
-            node.add_edge(edge_b.clone());
-            node.add_edge(edge_d.clone());
+            node.add_edge(edge_b.clone(), &Binary);
+            node.add_edge(edge_d.clone(), &Binary);
 
             // insert edge that should go in the middle
             let edge_c = Edge {
                 label: b'c',
                 node: Node::default().into(),
             };
-            node.add_edge(edge_c.clone());
+            node.add_edge(edge_c.clone(), &Binary);
 
-            let edges = node.edges.0.read();
+            let edges = &node.edges.0;
             let edges = edges.as_slice();
             assert_eq!(edges.len(), 3);
             assert_eq!(
@@ -612,7 +655,7 @@ mod tests {
 
     #[test]
     fn test_replace_edge() {
-        let node: Node<TestValue> = Node::default();
+        let mut node: Node<TestValue> = Node::default();
         let edge_a = Edge {
             label: b'a',
             node: Node::default().into(),
@@ -621,24 +664,25 @@ mod tests {
             label: b'b',
             node: Node::default().into(),
         };
-        node.add_edge(edge_a.clone());
-        node.add_edge(edge_b.clone());
+        node.add_edge(edge_a.clone(), &Binary);
+        node.add_edge(edge_b.clone(), &Binary);
 
         // replace edge 'a'
         let new_edge_a = Edge {
             label: b'a',
             node: Node {
-                leaf: RwLock::new(Some(Arc::new(LeafNode {
+                leaf: Some(Arc::new(LeafNode {
                     value: TestValue { data: "new".into() },
                     key: "new_key".into(),
-                }))),
+                    signal: Signal::default(),
+                })),
                 ..Default::default()
             }
             .into(),
         };
-        node.replace_edge(new_edge_a.clone());
+        node.replace_edge(new_edge_a.clone(), &Binary);
 
-        let edges = node.edges.0.read();
+        let edges = &node.edges.0;
         let edges = edges.as_slice();
         assert_eq!(edges.len(), 2);
         assert_eq!(edges[0], new_edge_a, "edge 'a' should be replaced");
@@ -647,7 +691,7 @@ mod tests {
 
     #[test]
     fn test_replace_edge_at() {
-        let node: Node<TestValue> = Node::default();
+        let mut node: Node<TestValue> = Node::default();
         let edge_a = Edge {
             label: b'a',
             node: Node::default().into(),
@@ -656,19 +700,20 @@ mod tests {
             label: b'b',
             node: Node::default().into(),
         };
-        node.add_edge(edge_a.clone());
-        node.add_edge(edge_b.clone());
+        node.add_edge(edge_a.clone(), &Binary);
+        node.add_edge(edge_b.clone(), &Binary);
 
         // replace edge at index 1 (edge 'b')
         let new_edge_b = Edge {
             label: b'b',
             node: Node {
-                leaf: RwLock::new(Some(Arc::new(LeafNode {
+                leaf: Some(Arc::new(LeafNode {
                     value: TestValue {
                         data: "new_b".into(),
                     },
                     key: "new_key_b".into(),
-                }))),
+                    signal: Signal::default(),
+                })),
                 ..Default::default()
             }
             .into(),
@@ -676,7 +721,7 @@ mod tests {
         node.replace_edge_at(1, new_edge_b.clone());
 
         {
-            let edges = node.edges.0.read();
+            let edges = &node.edges.0;
             let edges = edges.as_slice();
             assert_eq!(edges.len(), 2);
             assert_eq!(edges[0], edge_a, "edge 'a' should remain unchanged");
@@ -687,7 +732,7 @@ mod tests {
     #[test]
     #[should_panic(expected = "replace edge at invalid index or label mismatch")]
     fn test_replace_edge_invalid_index() {
-        let node: Node<TestValue> = Node::default();
+        let mut node: Node<TestValue> = Node::default();
         let edge_a = Edge {
             label: b'a',
             node: Node::default().into(),
@@ -696,8 +741,8 @@ mod tests {
             label: b'b',
             node: Node::default().into(),
         };
-        node.add_edge(edge_a);
-        node.add_edge(edge_b);
+        node.add_edge(edge_a, &Binary);
+        node.add_edge(edge_b, &Binary);
 
         // attempt to replace edge at invalid index
         node.replace_edge_at(
@@ -712,7 +757,7 @@ mod tests {
     #[test]
     #[should_panic(expected = "replace edge at invalid index or label mismatch")]
     fn test_replace_edge_with_invalid_label() {
-        let node: Node<TestValue> = Node::default();
+        let mut node: Node<TestValue> = Node::default();
         let edge_a = Edge {
             label: b'a',
             node: Node::default().into(),
@@ -721,8 +766,8 @@ mod tests {
             label: b'b',
             node: Node::default().into(),
         };
-        node.add_edge(edge_a);
-        node.add_edge(edge_b);
+        node.add_edge(edge_a, &Binary);
+        node.add_edge(edge_b, &Binary);
 
         // attempt to replace edge with invalid label
         node.replace_edge_at(
@@ -737,24 +782,24 @@ mod tests {
     #[test]
     #[should_panic(expected = "replace missing edge")]
     fn test_replace_missing_edge() {
-        let node: Node<TestValue> = Node::default();
+        let mut node: Node<TestValue> = Node::default();
         let edge_a = Edge {
             label: b'a',
             node: Node::default().into(),
         };
-        node.add_edge(edge_a);
+        node.add_edge(edge_a, &Binary);
 
         // attempt to replace non-existent edge 'b'
         let edge_b = Edge {
             label: b'b',
             node: Node::default().into(),
         };
-        node.replace_edge(edge_b);
+        node.replace_edge(edge_b, &Binary);
     }
 
     #[test]
     fn test_get_edge() {
-        let node: Node<TestValue> = Node::default();
+        let mut node: Node<TestValue> = Node::default();
         let edge_a = Edge {
             label: b'a',
             node: Node::default().into(),
@@ -763,11 +808,11 @@ mod tests {
             label: b'b',
             node: Node::default().into(),
         };
-        node.add_edge(edge_a.clone());
-        node.add_edge(edge_b.clone());
+        node.add_edge(edge_a.clone(), &Binary);
+        node.add_edge(edge_b.clone(), &Binary);
 
         // get existing edge 'a'
-        let result = node.get_edge(b'a');
+        let result = node.get_edge(&b'a', &Binary);
         assert!(result.is_some(), "should find edge 'a'");
         let (idx, found_node) = result.unwrap();
         assert_eq!(idx, 0, "edge 'a' should be at index 0");
@@ -777,7 +822,7 @@ mod tests {
         );
 
         // get existing edge 'b'
-        let result = node.get_edge(b'b');
+        let result = node.get_edge(&b'b', &Binary);
         assert!(result.is_some(), "should find edge 'b'");
         let (idx, found_node) = result.unwrap();
         assert_eq!(idx, 1, "edge 'b' should be at index 1");
@@ -787,13 +832,13 @@ mod tests {
         );
 
         // get non-existent edge 'c'
-        let result = node.get_edge(b'c');
+        let result = node.get_edge(&b'c', &Binary);
         assert!(result.is_none(), "should not find edge 'c'");
     }
 
     #[test]
     fn test_get_edge_at() {
-        let node: Node<TestValue> = Node::default();
+        let mut node: Node<TestValue> = Node::default();
         let edge_a = Edge {
             label: b'a',
             node: Node::default().into(),
@@ -802,8 +847,8 @@ mod tests {
             label: b'b',
             node: Node::default().into(),
         };
-        node.add_edge(edge_a.clone());
-        node.add_edge(edge_b.clone());
+        node.add_edge(edge_a.clone(), &Binary);
+        node.add_edge(edge_b.clone(), &Binary);
 
         {
             // get edge at index 0
@@ -836,7 +881,7 @@ mod tests {
 
     #[test]
     fn test_get_lower_bound_edge() {
-        let node: Node<TestValue> = Node::default();
+        let mut node: Node<TestValue> = Node::default();
         let edge_a = Edge {
             label: b'a',
             node: Node::default().into(),
@@ -845,11 +890,11 @@ mod tests {
             label: b'c',
             node: Node::default().into(),
         };
-        node.add_edge(edge_a.clone());
-        node.add_edge(edge_c.clone());
+        node.add_edge(edge_a.clone(), &Binary);
+        node.add_edge(edge_c.clone(), &Binary);
 
         // get lower bound edge for 'b' (should return edge 'c')
-        let result = node.get_lower_bound_edge(b'b');
+        let result = node.get_lower_bound_edge(&b'b', &Binary);
         assert!(result.is_some(), "should find lower bound edge for 'b'");
         let (idx, found_node) = result.unwrap();
         assert_eq!(idx, 1, "lower bound edge for 'b' should be at index 1");
@@ -859,7 +904,7 @@ mod tests {
         );
 
         // get lower bound edge for 'c' (should return edge 'c')
-        let result = node.get_lower_bound_edge(b'c');
+        let result = node.get_lower_bound_edge(&b'c', &Binary);
         assert!(result.is_some(), "should find lower bound edge for 'c'");
         let (idx, found_node) = result.unwrap();
         assert_eq!(idx, 1, "lower bound edge for 'c' should be at index 1");
@@ -869,13 +914,13 @@ mod tests {
         );
 
         // get lower bound edge for 'd' (should return None)
-        let result = node.get_lower_bound_edge(b'd');
+        let result = node.get_lower_bound_edge(&b'd', &Binary);
         assert!(result.is_none(), "should not find lower bound edge for 'd'");
     }
 
     #[test]
     fn test_delete_edge() {
-        let node: Node<TestValue> = Node::default();
+        let mut node: Node<TestValue> = Node::default();
         let edge_a = Edge {
             label: b'a',
             node: Node::default().into(),
@@ -884,13 +929,13 @@ mod tests {
             label: b'b',
             node: Node::default().into(),
         };
-        node.add_edge(edge_a.clone());
-        node.add_edge(edge_b.clone());
+        node.add_edge(edge_a.clone(), &Binary);
+        node.add_edge(edge_b.clone(), &Binary);
 
         {
             // delete non-existent edge 'c' (should do nothing)
-            node.delete_edge(b'c');
-            let edges = node.edges.0.read();
+            node.delete_edge(&b'c', &Binary);
+            let edges = &node.edges.0;
             let edges = edges.as_slice();
             assert_eq!(edges.len(), 2, "both edges should remain");
             assert_eq!(edges[0], edge_a, "edge 'a' should remain");
@@ -899,8 +944,8 @@ mod tests {
 
         {
             // delete edge 'a'
-            node.delete_edge(b'a');
-            let edges = node.edges.0.read();
+            node.delete_edge(&b'a', &Binary);
+            let edges = &node.edges.0;
             let edges = edges.as_slice();
             assert_eq!(edges.len(), 1);
             assert_eq!(edges[0], edge_b, "only edge 'b' should remain");
@@ -908,28 +953,28 @@ mod tests {
 
         {
             // delete edge 'b'
-            node.delete_edge(b'b');
-            let edges = node.edges.0.read();
+            node.delete_edge(&b'b', &Binary);
+            let edges = &node.edges.0;
             let edges = edges.as_slice();
             assert_eq!(edges.len(), 0, "no edges should remain");
         }
     }
 
     fn get_test_tree() -> Node<TestValue> {
-        let root: Node<TestValue> = Node::default();
-        let edge_0 = Edge {
+        let mut root: Node<TestValue> = Node::default();
+        let mut edge_0 = Edge {
             label: b'0',
             node: Node::<TestValue> {
-                prefix: RwLock::new("0".into()),
+                prefix: "0".into(),
                 ..Default::default()
             }
             .into(),
         };
 
-        let edge_00 = Edge {
+        let mut edge_00 = Edge {
             label: b'0',
             node: Node::<TestValue> {
-                prefix: RwLock::new("0".into()),
+                prefix: "0".into(),
                 ..Default::default()
             }
             .into(),
@@ -938,13 +983,14 @@ mod tests {
         let edge_001 = Edge {
             label: b'1',
             node: Node {
-                prefix: RwLock::new("1".into()),
-                leaf: RwLock::new(Some(Arc::new(LeafNode {
+                prefix: "1".into(),
+                leaf: Some(Arc::new(LeafNode {
                     value: TestValue {
                         data: "value_001".into(),
                     },
                     key: "001".into(),
-                }))),
+                    signal: Signal::default(),
+                })),
                 ..Default::default()
             }
             .into(),
@@ -953,13 +999,14 @@ mod tests {
         let edge_002 = Edge {
             label: b'2',
             node: Node {
-                prefix: RwLock::new("2".into()),
-                leaf: RwLock::new(Some(Arc::new(LeafNode {
+                prefix: "2".into(),
+                leaf: Some(Arc::new(LeafNode {
                     value: TestValue {
                         data: "value_002".into(),
                     },
                     key: "002".into(),
-                }))),
+                    signal: Signal::default(),
+                })),
                 ..Default::default()
             }
             .into(),
@@ -968,13 +1015,14 @@ mod tests {
         let edge_003 = Edge {
             label: b'3',
             node: Node {
-                prefix: RwLock::new("3".into()),
-                leaf: RwLock::new(Some(Arc::new(LeafNode {
+                prefix: "3".into(),
+                leaf: Some(Arc::new(LeafNode {
                     value: TestValue {
                         data: "value_003".into(),
                     },
                     key: "003".into(),
-                }))),
+                    signal: Signal::default(),
+                })),
                 ..Default::default()
             }
             .into(),
@@ -983,13 +1031,14 @@ mod tests {
         let edge_010 = Edge {
             label: b'1',
             node: Node {
-                prefix: RwLock::new("10".into()),
-                leaf: RwLock::new(Some(Arc::new(LeafNode {
+                prefix: "10".into(),
+                leaf: Some(Arc::new(LeafNode {
                     value: TestValue {
                         data: "value_010".into(),
                     },
                     key: "010".into(),
-                }))),
+                    signal: Signal::default(),
+                })),
                 ..Default::default()
             }
             .into(),
@@ -998,25 +1047,36 @@ mod tests {
         let edge_100 = Edge {
             label: b'1',
             node: Node {
-                prefix: RwLock::new("100".into()),
-                leaf: RwLock::new(Some(Arc::new(LeafNode {
+                prefix: "100".into(),
+                leaf: Some(Arc::new(LeafNode {
                     value: TestValue {
                         data: "value_100".into(),
                     },
                     key: "100".into(),
-                }))),
+                    signal: Signal::default(),
+                })),
                 ..Default::default()
             }
             .into(),
         };
 
-        edge_00.node.add_edge(edge_001);
-        edge_00.node.add_edge(edge_002);
-        edge_00.node.add_edge(edge_003);
-        edge_0.node.add_edge(edge_010);
-        edge_0.node.add_edge(edge_00);
-        root.add_edge(edge_0);
-        root.add_edge(edge_100);
+        Arc::get_mut(&mut edge_00.node)
+            .unwrap()
+            .add_edge(edge_001, &Binary);
+        Arc::get_mut(&mut edge_00.node)
+            .unwrap()
+            .add_edge(edge_002, &Binary);
+        Arc::get_mut(&mut edge_00.node)
+            .unwrap()
+            .add_edge(edge_003, &Binary);
+        Arc::get_mut(&mut edge_0.node)
+            .unwrap()
+            .add_edge(edge_010, &Binary);
+        Arc::get_mut(&mut edge_0.node)
+            .unwrap()
+            .add_edge(edge_00, &Binary);
+        root.add_edge(edge_0, &Binary);
+        root.add_edge(edge_100, &Binary);
 
         root
     }
@@ -1026,7 +1086,7 @@ mod tests {
         let root = get_test_tree();
 
         {
-            let result = root.get("001");
+            let result = root.get(b"001", &Binary);
             assert_eq!(
                 result,
                 Some(TestValue {
@@ -1036,7 +1096,7 @@ mod tests {
         }
 
         {
-            let result = root.get("100");
+            let result = root.get(b"100", &Binary);
             assert_eq!(
                 result,
                 Some(TestValue {
@@ -1046,7 +1106,7 @@ mod tests {
         }
 
         {
-            let result = root.get("002");
+            let result = root.get(b"002", &Binary);
             assert_eq!(
                 result,
                 Some(TestValue {
@@ -1056,7 +1116,7 @@ mod tests {
         }
 
         {
-            let result = root.get("003");
+            let result = root.get(b"003", &Binary);
             assert_eq!(
                 result,
                 Some(TestValue {
@@ -1066,7 +1126,7 @@ mod tests {
         }
 
         {
-            let result = root.get("010");
+            let result = root.get(b"010", &Binary);
             assert_eq!(
                 result,
                 Some(TestValue {
@@ -1076,17 +1136,17 @@ mod tests {
         }
 
         {
-            let result = root.get("01");
+            let result = root.get(b"01", &Binary);
             assert_eq!(result, None);
         }
 
         {
-            let result = root.get("00");
+            let result = root.get(b"00", &Binary);
             assert_eq!(result, None);
         }
 
         {
-            let result = root.get("0");
+            let result = root.get(b"0", &Binary);
             assert_eq!(result, None);
         }
     }
@@ -1096,7 +1156,7 @@ mod tests {
         let root = get_test_tree();
 
         {
-            let result = root.longest_prefix("00123");
+            let result = root.longest_prefix(b"00123", &Binary);
             assert_eq!(
                 result,
                 Some((
@@ -1109,7 +1169,7 @@ mod tests {
         }
 
         {
-            let result = root.longest_prefix("003");
+            let result = root.longest_prefix(b"003", &Binary);
             assert_eq!(
                 result,
                 Some((
@@ -1122,7 +1182,7 @@ mod tests {
         }
 
         {
-            let result = root.longest_prefix("10099");
+            let result = root.longest_prefix(b"10099", &Binary);
             assert_eq!(
                 result,
                 Some((
@@ -1135,7 +1195,7 @@ mod tests {
         }
 
         {
-            let result = root.longest_prefix("002abc");
+            let result = root.longest_prefix(b"002abc", &Binary);
             assert_eq!(
                 result,
                 Some((
@@ -1148,7 +1208,7 @@ mod tests {
         }
 
         {
-            let result = root.longest_prefix("010abc");
+            let result = root.longest_prefix(b"010abc", &Binary);
             assert_eq!(
                 result,
                 Some((
@@ -1161,17 +1221,17 @@ mod tests {
         }
 
         {
-            let result = root.longest_prefix("011abc");
+            let result = root.longest_prefix(b"011abc", &Binary);
             assert_eq!(result, None);
         }
 
         {
-            let result = root.longest_prefix("0");
+            let result = root.longest_prefix(b"0", &Binary);
             assert_eq!(result, None);
         }
 
         {
-            let result = root.longest_prefix("2");
+            let result = root.longest_prefix(b"2", &Binary);
             assert_eq!(result, None);
         }
     }
@@ -1247,7 +1307,7 @@ mod tests {
             let first_edge_node = root.first_edge();
             assert!(first_edge_node.is_some());
             let first_edge_node = first_edge_node.unwrap();
-            assert_eq!(first_edge_node.prefix.read().as_str(), "0");
+            assert_eq!(first_edge_node.prefix.as_slice(), b"0");
         }
 
         {
@@ -1267,7 +1327,7 @@ mod tests {
             let last_edge_node = root.last_edge();
             assert!(last_edge_node.is_some());
             let last_edge_node = last_edge_node.unwrap();
-            assert_eq!(last_edge_node.prefix.read().as_str(), "100");
+            assert_eq!(last_edge_node.prefix.as_slice(), b"100");
         }
 
         {
@@ -1282,7 +1342,7 @@ mod tests {
     fn test_clear_edges() {
         {
             // node with edges
-            let root = get_test_tree();
+            let mut root = get_test_tree();
             assert_eq!(root.edge_len(), 2, "node should have 2 edges before clear");
 
             root.clear_edges();
@@ -1291,7 +1351,7 @@ mod tests {
 
         {
             // empty node
-            let root = Node::<TestValue>::default();
+            let mut root = Node::<TestValue>::default();
             assert_eq!(
                 root.edge_len(),
                 0,
@@ -1310,7 +1370,7 @@ mod tests {
     #[test]
     fn test_reset_edges() {
         {
-            let node = get_test_tree();
+            let mut node = get_test_tree();
             assert_eq!(node.edge_len(), 2, "node should have 2 edges before reset");
 
             node.reset_edges();
@@ -1319,7 +1379,7 @@ mod tests {
 
         {
             // empty node
-            let node: Node<TestValue> = Node::default();
+            let mut node: Node<TestValue> = Node::default();
             assert_eq!(
                 node.edge_len(),
                 0,
@@ -1339,7 +1399,7 @@ mod tests {
     fn test_pop_edges() {
         {
             // node with edges
-            let source_node = get_test_tree();
+            let mut source_node = get_test_tree();
             assert_eq!(
                 source_node.edge_len(),
                 2,
@@ -1375,7 +1435,7 @@ mod tests {
 
         {
             // empty node
-            let source_node: Node<TestValue> = Node::default();
+            let mut source_node: Node<TestValue> = Node::default();
             assert_eq!(
                 source_node.edge_len(),
                 0,
@@ -1397,7 +1457,7 @@ mod tests {
         {
             // collect edges from one node to another
             let source_node = get_test_tree();
-            let target_node: Node<TestValue> = Node::default();
+            let mut target_node: Node<TestValue> = Node::default();
 
             assert_eq!(
                 source_node.edge_len(),
@@ -1410,24 +1470,31 @@ mod tests {
                 "target node should have 0 edges before collect"
             );
 
-            source_node.collect_into_edges(&target_node.edges);
+            source_node.collect_into_edges(&mut target_node.edges);
 
             assert_eq!(
                 source_node.edge_len(),
-                0,
-                "source node should have 0 edges after collect"
+                2,
+                "source node should keep its 2 edges after collect"
             );
             assert_eq!(
                 target_node.edge_len(),
                 2,
                 "target node should have 2 edges after collect"
             );
+            assert!(
+                Arc::ptr_eq(
+                    &source_node.first_edge().unwrap(),
+                    &target_node.first_edge().unwrap()
+                ),
+                "collected edges should share the child nodes"
+            );
         }
 
         {
             // collect edges from empty node to another
             let source_node: Node<TestValue> = Node::default();
-            let target_node: Node<TestValue> = Node::default();
+            let mut target_node: Node<TestValue> = Node::default();
 
             assert_eq!(
                 source_node.edge_len(),
@@ -1440,7 +1507,7 @@ mod tests {
                 "target node should have 0 edges before collect"
             );
 
-            source_node.collect_into_edges(&target_node.edges);
+            source_node.collect_into_edges(&mut target_node.edges);
 
             assert_eq!(
                 source_node.edge_len(),
@@ -1463,4 +1530,141 @@ mod tests {
         mock_tree.for_each_edge(|edge| tree_labels.push(edge.label));
         assert_eq!(tree_labels, vec![b'0', b'1'], "edge labels should match");
     }
+
+    /// Orders the labels in reverse, so the edges are not in byte order.
+    #[derive(Debug)]
+    struct Reverse;
+
+    impl Collation<u8> for Reverse {
+        fn compare(&self, a: &u8, b: &u8) -> Ordering {
+            b.cmp(a)
+        }
+    }
+
+    /// Orders the digits after the other labels, so new edges may fall outside the byte order.
+    #[derive(Debug)]
+    struct DigitsLast;
+
+    impl Collation<u8> for DigitsLast {
+        fn compare(&self, a: &u8, b: &u8) -> Ordering {
+            (a.is_ascii_digit(), a).cmp(&(b.is_ascii_digit(), b))
+        }
+    }
+
+    /// Returns the layout of the edges of the node.
+    fn edge_layout<L: crate::utils::EdgeLabel>(node: &Node<TestValue, L>) -> &'static str {
+        match node.edges.1 {
+            EdgeIndex::Node4 => "Node4",
+            EdgeIndex::Node16 => "Node16",
+            EdgeIndex::Node48(_) => "Node48",
+            EdgeIndex::Node256(_) => "Node256",
+        }
+    }
+
+    #[test]
+    fn test_adaptive_edges() {
+        let mut node: Node<TestValue> = Node::default();
+        let layouts = [
+            (4, "Node4"),
+            (16, "Node16"),
+            (48, "Node48"),
+            (128, "Node256"),
+        ];
+
+        // add every even byte in a scattered order, growing through the layouts
+        let labels: Vec<u8> = (0..128u8).map(|i| i.wrapping_mul(37) % 128 * 2).collect();
+        for (count, label) in labels.iter().enumerate() {
+            node.add_edge(Edge::new(*label, Node::default().into()), &Binary);
+            let layout = layouts.iter().find(|(max, _)| count < *max).unwrap().1;
+            assert_eq!(
+                edge_layout(&node),
+                layout,
+                "layout with {} edges",
+                count + 1
+            );
+
+            for probe in [0, *label, label | 1, 254, 255] {
+                let expected = node.edges().iter().position(|edge| edge.label >= probe);
+                assert_eq!(
+                    node.get_lower_bound_edge(&probe, &Binary)
+                        .map(|(idx, _)| idx),
+                    expected,
+                    "lower bound of {probe} with {} edges",
+                    count + 1
+                );
+                assert_eq!(
+                    node.find_edge(&probe, &Binary).map(|(idx, _)| idx),
+                    expected.filter(|idx| node.edges()[*idx].label == probe),
+                    "edge {probe} with {} edges",
+                    count + 1
+                );
+            }
+        }
+        assert_eq!(node.edges.0.capacity(), 256);
+        assert!(node.edges().is_sorted_by_key(|edge| edge.label));
+        assert_eq!(node.first_edge().unwrap().prefix.as_slice(), b"");
+        assert_eq!(node.edges().first().unwrap().label, 0);
+        assert_eq!(node.edges().last().unwrap().label, 254);
+
+        // delete the edges again, shrinking back through the layouts
+        for (count, label) in labels.iter().enumerate().rev() {
+            node.delete_edge(label, &Binary);
+            assert!(node.find_edge(label, &Binary).is_none());
+            let layout = layouts.iter().find(|(max, _)| count <= *max).unwrap().1;
+            assert_eq!(edge_layout(&node), layout, "layout with {count} edges");
+        }
+        assert!(node.edges.0.capacity() <= 4, "empty edges should shrink");
+
+        // edges out of byte order are not indexed by rank
+        let mut reversed: Node<TestValue> = Node::default();
+        for label in 0..64u8 {
+            reversed.add_edge(Edge::new(label, Node::default().into()), &Reverse);
+        }
+        assert_eq!(edge_layout(&reversed), "Node16");
+        assert_eq!(reversed.edges().first().unwrap().label, 63);
+        assert_eq!(
+            reversed
+                .get_lower_bound_edge(&10, &Reverse)
+                .map(|(idx, _)| idx),
+            Some(53)
+        );
+
+        // edges missing from the ranks are placed by the collation
+        let mut letters: Node<TestValue> = Node::default();
+        for label in (b'a'..=b'z').chain(b'A'..=b'Z') {
+            letters.add_edge(Edge::new(label, Node::default().into()), &DigitsLast);
+        }
+        assert_eq!(edge_layout(&letters), "Node256");
+        assert_eq!(
+            letters
+                .get_lower_bound_edge(&b'5', &DigitsLast)
+                .map(|(idx, _)| idx),
+            None
+        );
+        for label in *b"50" {
+            letters.add_edge(Edge::new(label, Node::default().into()), &DigitsLast);
+        }
+        let labels: Vec<u8> = letters.edges().iter().map(|edge| edge.label).collect();
+        assert!(labels.is_sorted_by(|a, b| DigitsLast.compare(a, b).is_le()));
+        assert_eq!(labels[..2], *b"AB");
+        assert_eq!(labels[52..], *b"05");
+        assert_eq!(
+            letters.find_edge(&b'0', &DigitsLast).map(|(idx, _)| idx),
+            Some(52)
+        );
+        assert_eq!(
+            letters
+                .get_lower_bound_edge(&b'3', &DigitsLast)
+                .map(|(idx, _)| idx),
+            Some(53)
+        );
+
+        // edges which are not labelled by bytes are always searched
+        let mut chars: Node<TestValue, char> = Node::default();
+        for label in ('a'..='z').chain('A'..='Z') {
+            chars.add_edge(Edge::new(label, Node::default().into()), &Binary);
+        }
+        assert_eq!(edge_layout(&chars), "Node16");
+        assert_eq!(chars.find_edge(&'q', &Binary).map(|(idx, _)| idx), Some(42));
+    }
 }
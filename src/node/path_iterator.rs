@@ -1,54 +1,71 @@
-use std::rc::Rc;
+use std::sync::Arc;
 
-use crate::node::{LeafNode, Node};
+use crate::{
+    collation::Collation,
+    node::{LeafNode, Node},
+    utils::{EdgeLabel, NodeValue},
+};
 
-pub struct PathIterator<T>
+/// Iterates over the leaves along the path of a key, from the root to the key itself.
+///
+/// Every yielded leaf holds a key which is a prefix of the searched key.
+pub struct PathIterator<T, L = u8>
 where
-    T: Clone + Default,
+    T: NodeValue,
+    L: EdgeLabel,
 {
-    pub(crate) node: Option<Rc<Node<T>>>,
-    pub(crate) path: &'static str,
-}
+    // next node on the path
+    node: Option<Arc<Node<T, L>>>,
 
-impl<T: Clone + Default> Iterator for PathIterator<T> {
-    type Item = (&'static str, T);
+    // normalized key and the length of it matched by the visited nodes
+    path: Vec<L>,
+    offset: usize,
 
-    fn next(&mut self) -> Option<Self::Item> {
-        let mut leaf: Option<LeafNode<T>> = None;
-        while leaf.is_none() && self.node.is_some() {
-            if self.node.as_ref().unwrap().leaf.is_some() {
-                leaf = self.node.as_ref().unwrap().leaf.clone();
-            }
-            self.iterate();
-        }
-        if leaf.is_some() {
-            return Some((
-                leaf.as_ref().unwrap().key,
-                leaf.as_ref().unwrap().value.clone(),
-            ));
+    // collation used to match the edges of the tree
+    collation: Arc<dyn Collation<L>>,
+}
+
+impl<T: NodeValue, L: EdgeLabel> PathIterator<T, L> {
+    /// Creates an iterator over the leaves along the given (normalized) key.
+    pub(crate) fn new(
+        node: Arc<Node<T, L>>,
+        collation: Arc<dyn Collation<L>>,
+        path: Vec<L>,
+    ) -> Self {
+        Self {
+            node: Some(node),
+            path,
+            offset: 0,
+            collation,
         }
-        None
     }
-}
 
-impl<T: Clone + Default> PathIterator<T> {
-    fn iterate(&mut self) {
-        if self.path.len() == 0 {
-            self.node = None;
+    /// Moves to the child of the given node along the path if it matches.
+    fn iterate(&mut self, node: &Node<T, L>) {
+        let search = &self.path[self.offset..];
+        if search.is_empty() {
             return;
         }
 
-        if let Some((_, edge)) = self
-            .node
-            .as_ref()
-            .unwrap()
-            .get_edge(self.path.as_bytes()[0])
+        if let Some((_, child_node)) = node.find_edge(&search[0], self.collation.as_ref())
+            && search.starts_with(&child_node.prefix)
         {
-            if self.path.starts_with(edge.node.prefix) {
-                self.path = &self.path[edge.node.prefix.len()..];
-            } else {
-                self.node = None;
+            self.offset += child_node.prefix.len();
+            self.node = Some(child_node.clone());
+        }
+    }
+}
+
+impl<T: NodeValue, L: EdgeLabel> Iterator for PathIterator<T, L> {
+    type Item = Arc<LeafNode<T, L>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(node) = self.node.take() {
+            self.iterate(&node);
+            if let Some(leaf) = node.leaf.as_ref() {
+                return Some(leaf.clone());
             }
         }
+        None
     }
 }
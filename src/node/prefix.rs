@@ -0,0 +1,295 @@
+use std::{
+    fmt,
+    hash::{Hash, Hasher},
+    marker::PhantomData,
+    mem::{self, ManuallyDrop, MaybeUninit},
+    ops::Deref,
+    ptr::{self, NonNull},
+    slice,
+};
+
+/// Number of bytes of labels stored inline, which overlap the pointer and capacity of the heap.
+const INLINE_BYTES: usize = 2 * mem::size_of::<usize>();
+
+/// The prefix of a node.
+///
+/// Short prefixes, e.g. up to 16 bytes on 64-bit targets, are stored inline in the node and only
+/// longer ones are allocated on the heap, while the prefix takes as much space as a `Vec<L>`.
+/// Labels too large or too aligned to fit inline are always stored on the heap.
+pub(crate) struct Prefix<L> {
+    // number of labels, which are stored inline while it fits the inline capacity
+    len: usize,
+    data: Data<L>,
+    _marker: PhantomData<L>,
+}
+
+union Data<L> {
+    inline: [MaybeUninit<usize>; 2],
+    heap: (NonNull<L>, usize),
+}
+
+// the prefix owns its labels like a `Vec<L>` does
+unsafe impl<L: Send> Send for Prefix<L> {}
+unsafe impl<L: Sync> Sync for Prefix<L> {}
+
+impl<L> Prefix<L> {
+    /// Number of labels which can be stored inline.
+    const INLINE_CAPACITY: usize =
+        if mem::size_of::<L>() == 0 || mem::align_of::<L>() > mem::align_of::<usize>() {
+            0
+        } else {
+            INLINE_BYTES / mem::size_of::<L>()
+        };
+
+    /// Returns true if the labels are stored inline.
+    fn is_inline(&self) -> bool {
+        self.len <= Self::INLINE_CAPACITY
+    }
+
+    fn inline_ptr(&self) -> *const L {
+        unsafe { self.data.inline.as_ptr().cast() }
+    }
+
+    fn inline_mut_ptr(&mut self) -> *mut L {
+        unsafe { self.data.inline.as_mut_ptr().cast() }
+    }
+
+    /// Creates a prefix from the given labels, moving them inline if they fit.
+    fn from_vec(vec: Vec<L>) -> Self {
+        let mut vec = ManuallyDrop::new(vec);
+        let mut prefix = Self::default();
+        if vec.len() <= Self::INLINE_CAPACITY {
+            unsafe {
+                ptr::copy_nonoverlapping(vec.as_ptr(), prefix.inline_mut_ptr(), vec.len());
+                prefix.len = vec.len();
+                // the labels are moved, only the allocation is left to free
+                vec.set_len(0);
+                ManuallyDrop::drop(&mut vec);
+            }
+        } else {
+            let ptr = NonNull::new(vec.as_mut_ptr()).expect("vec pointer is never null");
+            prefix.data.heap = (ptr, vec.capacity());
+            prefix.len = vec.len();
+        }
+        prefix
+    }
+
+    /// Takes the labels out as a vector, allocating it if they are stored inline.
+    fn into_vec(self) -> Vec<L> {
+        let prefix = ManuallyDrop::new(self);
+        if prefix.is_inline() {
+            let mut vec = Vec::with_capacity(prefix.len);
+            unsafe {
+                ptr::copy_nonoverlapping(prefix.inline_ptr(), vec.as_mut_ptr(), prefix.len);
+                vec.set_len(prefix.len);
+            }
+            vec
+        } else {
+            let (ptr, capacity) = unsafe { prefix.data.heap };
+            unsafe { Vec::from_raw_parts(ptr.as_ptr(), prefix.len, capacity) }
+        }
+    }
+
+    /// Returns the labels as a slice.
+    pub(crate) fn as_slice(&self) -> &[L] {
+        if self.is_inline() {
+            unsafe { slice::from_raw_parts(self.inline_ptr(), self.len) }
+        } else {
+            unsafe { slice::from_raw_parts(self.data.heap.0.as_ptr(), self.len) }
+        }
+    }
+
+    /// Removes the first `count` labels, keeping the rest. Short prefixes are moved in place.
+    pub(crate) fn remove_front(&mut self, count: usize) {
+        assert!(
+            count <= self.len,
+            "cannot remove more labels than the prefix has"
+        );
+        if self.is_inline() {
+            let len = mem::replace(&mut self.len, 0);
+            unsafe {
+                let labels = self.inline_mut_ptr();
+                ptr::drop_in_place(ptr::slice_from_raw_parts_mut(labels, count));
+                ptr::copy(labels.add(count), labels, len - count);
+            }
+            self.len = len - count;
+        } else {
+            let mut vec = mem::take(self).into_vec();
+            vec.drain(..count);
+            *self = Self::from_vec(vec);
+        }
+    }
+
+    /// Appends the given labels. Short prefixes stay inline without allocating.
+    pub(crate) fn extend_from_slice(&mut self, labels: &[L])
+    where
+        L: Clone,
+    {
+        if self.len + labels.len() <= Self::INLINE_CAPACITY {
+            for label in labels {
+                unsafe { self.inline_mut_ptr().add(self.len).write(label.clone()) };
+                self.len += 1;
+            }
+        } else {
+            let mut vec = mem::take(self).into_vec();
+            vec.extend_from_slice(labels);
+            *self = Self::from_vec(vec);
+        }
+    }
+}
+
+impl<L> Default for Prefix<L> {
+    fn default() -> Self {
+        Self {
+            len: 0,
+            data: Data {
+                inline: [MaybeUninit::uninit(); 2],
+            },
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<L> Drop for Prefix<L> {
+    fn drop(&mut self) {
+        if self.is_inline() {
+            unsafe {
+                ptr::drop_in_place(ptr::slice_from_raw_parts_mut(
+                    self.inline_mut_ptr(),
+                    self.len,
+                ))
+            };
+        } else {
+            drop(mem::take(self).into_vec());
+        }
+    }
+}
+
+impl<L> Deref for Prefix<L> {
+    type Target = [L];
+
+    fn deref(&self) -> &[L] {
+        self.as_slice()
+    }
+}
+
+impl<L: Clone> From<&[L]> for Prefix<L> {
+    fn from(labels: &[L]) -> Self {
+        if labels.len() <= Self::INLINE_CAPACITY {
+            let mut prefix = Self::default();
+            prefix.extend_from_slice(labels);
+            prefix
+        } else {
+            Self::from_vec(labels.to_vec())
+        }
+    }
+}
+
+impl<L> From<Vec<L>> for Prefix<L> {
+    fn from(vec: Vec<L>) -> Self {
+        Self::from_vec(vec)
+    }
+}
+
+impl From<&str> for Prefix<u8> {
+    fn from(prefix: &str) -> Self {
+        Self::from(prefix.as_bytes())
+    }
+}
+
+impl<L: Clone> Clone for Prefix<L> {
+    fn clone(&self) -> Self {
+        Self::from(self.as_slice())
+    }
+}
+
+impl<L: fmt::Debug> fmt::Debug for Prefix<L> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.as_slice().fmt(f)
+    }
+}
+
+impl<L: Hash> Hash for Prefix<L> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_slice().hash(state);
+    }
+}
+
+impl<L: PartialEq> PartialEq for Prefix<L> {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_slice() == other.as_slice()
+    }
+}
+
+impl<L: Eq> Eq for Prefix<L> {}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use super::*;
+
+    #[test]
+    fn test_prefix_inline_and_heap() {
+        assert_eq!(mem::size_of::<Prefix<u8>>(), mem::size_of::<Vec<u8>>());
+        assert_eq!(Prefix::<u8>::INLINE_CAPACITY, INLINE_BYTES);
+        assert_eq!(Prefix::<String>::INLINE_CAPACITY, 0);
+
+        let short = Prefix::from("short");
+        assert!(short.is_inline());
+        assert_eq!(short.as_slice(), b"short");
+
+        let long = Prefix::from("a prefix longer than the inline capacity");
+        assert!(!long.is_inline());
+        assert_eq!(long.as_slice(), b"a prefix longer than the inline capacity");
+        assert_eq!(long.clone(), long);
+        assert_ne!(long, short);
+    }
+
+    #[test]
+    fn test_prefix_split_and_merge() {
+        // splitting and merging short prefixes happens inline
+        let mut prefix = Prefix::from("splitting");
+        prefix.remove_front(5);
+        assert!(prefix.is_inline());
+        assert_eq!(prefix.as_slice(), b"ting");
+        prefix.extend_from_slice(b"s");
+        assert_eq!(prefix.as_slice(), b"tings");
+
+        // a merge moves the prefix to the heap once it is too long
+        prefix.extend_from_slice(b" that are longer than inline");
+        assert!(!prefix.is_inline());
+        assert_eq!(prefix.as_slice(), b"tings that are longer than inline");
+
+        // and a split moves it back inline once it is short enough
+        prefix.remove_front(15);
+        assert!(!prefix.is_inline());
+        prefix.remove_front(7);
+        assert!(prefix.is_inline());
+        assert_eq!(prefix.as_slice(), b"than inline");
+
+        let mut segments = Prefix::from(vec!["usr".to_string(), "local".to_string()]);
+        segments.remove_front(1);
+        segments.extend_from_slice(&["bin".to_string()]);
+        assert_eq!(segments.as_slice(), ["local", "bin"]);
+    }
+
+    #[test]
+    fn test_prefix_drops_labels() {
+        let label = Rc::new(0u8);
+        {
+            let mut inline = Prefix::from(vec![label.clone(), label.clone()]);
+            assert!(inline.is_inline());
+            inline.remove_front(1);
+            assert_eq!(Rc::strong_count(&label), 2);
+
+            let mut heap = Prefix::from(vec![label.clone(); 4]);
+            assert!(!heap.is_inline());
+            heap.extend_from_slice(std::slice::from_ref(&label));
+            heap.remove_front(4);
+            assert!(heap.is_inline());
+            assert_eq!(Rc::strong_count(&label), 3);
+        }
+        assert_eq!(Rc::strong_count(&label), 1);
+    }
+}
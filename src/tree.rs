@@ -1,59 +1,301 @@
+mod arena;
+mod change_set;
+mod changefeed;
+mod entry;
+mod frozen;
+mod multimap;
 mod transaction;
 #[cfg(test)]
 mod tree_test;
 
-use std::sync::Arc;
+use std::{ops::RangeBounds, sync::Arc};
 
-use parking_lot::lock_api::RwLock;
-
-use crate::{node::Node, tree::transaction::Txn, utils::NodeValue};
+pub use self::{
+    arena::NodeArena,
+    change_set::{Change, ChangeKind, ChangeSet},
+    changefeed::{ChangefeedEntry, Lineage, Subscriber},
+    entry::{Entry, OccupiedEntry, VacantEntry},
+    frozen::{FrozenIter, FrozenTree},
+    multimap::{MultiTree, MultiTxn, Pairs, Values, ValuesIter},
+    transaction::Savepoint,
+};
+use crate::{
+    aggregate::Monoid,
+    collation::{Binary, Collation},
+    node::{Iter, LeafNode, Node, PathIterator, Range},
+    tree::transaction::Txn,
+    utils::normalize_bound,
+    utils::{EdgeLabel, NodeValue},
+    watch::Watch,
+};
 
 /// Immutable radix tree with prefix based lookup.
-#[derive(Debug, PartialEq, Eq)]
-pub struct Tree<T>
+///
+/// Keys are sequences of `L` symbols; each edge of the tree branches on a single symbol.
+/// The default `u8` label makes the tree byte oriented, so `&str` and `&[u8]` can be used as keys.
+/// Versions of a tree share every subtree they have in common, so cloning a tree is cheap.
+#[derive(Debug, Clone)]
+pub struct Tree<T, L = u8>
 where
     T: NodeValue,
+    L: EdgeLabel,
 {
-    root: Arc<Node<T>>,
+    root: Arc<Node<T, L>>,
     size: u32,
+    seq: u64,
+    collation: Arc<dyn Collation<L>>,
+    monoid: Option<Arc<dyn Monoid<T>>>,
+    lineage: Arc<Lineage<T, L>>,
+    arena: Option<NodeArena<T, L>>,
+}
+
+impl<T: NodeValue + PartialEq, L: EdgeLabel> PartialEq for Tree<T, L> {
+    fn eq(&self, other: &Self) -> bool {
+        self.size == other.size && self.root == other.root
+    }
+}
+
+impl<T: NodeValue + Eq, L: EdgeLabel> Eq for Tree<T, L> {}
+
+impl<T: NodeValue, L: EdgeLabel> Drop for Tree<T, L> {
+    fn drop(&mut self) {
+        // only the last tree holding the root hands its subtrees back to the arena, while the
+        // root itself is dropped with the tree
+        if let Some(arena) = self.arena.as_ref()
+            && let Some(root) = Arc::get_mut(&mut self.root)
+        {
+            let mut children = Vec::new();
+            root.release_into(&mut children);
+            arena.release(children);
+        }
+    }
 }
 
-impl<T: NodeValue> Tree<T> {
+impl<T: NodeValue, L: EdgeLabel> Default for Tree<T, L> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: NodeValue, L: EdgeLabel> Tree<T, L> {
     /// Create a new empty tree.
     pub fn new() -> Self {
+        Self::with_collation(Binary)
+    }
+
+    /// Create a new empty tree which matches and orders its keys with the given collation.
+    pub fn with_collation(collation: impl Collation<L> + 'static) -> Self {
         Tree {
             root: Node::default().into(),
             size: 0,
+            seq: 0,
+            collation: Arc::new(collation),
+            monoid: None,
+            lineage: Arc::default(),
+            arena: None,
         }
     }
 
+    /// Maintain an aggregate of the values of every subtree with the given monoid,
+    /// which is read with [`Tree::aggregate_prefix`].
+    ///
+    /// The aggregates of a non-empty tree are computed bottom-up over a copy of its nodes, as the
+    /// nodes are shared with the other versions of the tree.
+    pub fn with_aggregate(mut self, monoid: impl Monoid<T> + 'static) -> Self {
+        let monoid: Arc<dyn Monoid<T>> = Arc::new(monoid);
+        if !self.is_empty() {
+            let root = self.root.rebuild(&mut Arc::clone, Some(monoid.as_ref()));
+            self.root = Arc::new(root);
+        }
+        self.monoid = Some(monoid);
+        self
+    }
+
+    /// Create the nodes of the transactions of the tree and its later versions from the given
+    /// arena, which the nodes are handed back to once the trees are dropped.
+    pub fn with_arena(mut self, arena: &NodeArena<T, L>) -> Self {
+        self.arena = Some(arena.clone());
+        self
+    }
+
     /// Get the number of node in the tree.
     pub fn len(&self) -> u32 {
         self.size
     }
 
+    /// Returns true if the tree contains no keys.
+    pub fn is_empty(&self) -> bool {
+        self.size == 0
+    }
+
     /// Get the root node of the tree.
-    pub fn root(&self) -> Arc<Node<T>> {
+    pub fn root(&self) -> Arc<Node<T, L>> {
         self.root.clone()
     }
 
+    /// Get the collation used to match and order the keys of the tree.
+    pub fn collation(&self) -> &dyn Collation<L> {
+        self.collation.as_ref()
+    }
+
     /// Get the value associated with the given key if exists.
-    pub fn get(&self, key: &str) -> Option<T> {
-        self.root.get(key)
+    pub fn get(&self, key: impl AsRef<[L]>) -> Option<T> {
+        let search = self.collation.normalize(key.as_ref());
+        self.root.get(&search, self.collation.as_ref())
+    }
+
+    /// Get the leaf holding the given key if exists, sharing it with the tree instead of cloning
+    /// the value.
+    pub fn get_ref(&self, key: impl AsRef<[L]>) -> Option<Arc<LeafNode<T, L>>> {
+        let search = self.collation.normalize(key.as_ref());
+        self.root.get_leaf(&search, self.collation.as_ref())
+    }
+
+    /// Call the given function with a reference to the value of the given key if exists.
+    pub fn with_value<R>(&self, key: impl AsRef<[L]>, f: impl FnOnce(&T) -> R) -> Option<R> {
+        self.get_ref(key).map(|leaf| f(leaf.get_value()))
+    }
+
+    /// Get the value of the given key if exists, with a watch which fires when a committed
+    /// transaction changes the key. A watch of a missing key fires when it may have been inserted.
+    pub fn get_watch(&self, key: impl AsRef<[L]>) -> (Watch, Option<T>) {
+        let search = self.collation.normalize(key.as_ref());
+        let (watch, leaf) = self.root.get_watch(&search, self.collation.as_ref());
+        (watch, leaf.map(|leaf| leaf.get_value().clone()))
+    }
+
+    /// Get a watch which fires when a committed transaction changes any key with the given prefix.
+    pub fn watch_prefix(&self, prefix: impl AsRef<[L]>) -> Watch {
+        let search = self.collation.normalize(prefix.as_ref());
+        self.root.watch_prefix(&search, self.collation.as_ref())
+    }
+
+    /// Iterate over all the leaves of the tree in key order.
+    pub fn iter(&self) -> Iter<T, L> {
+        Iter::new(self.root.clone(), self.collation.clone())
+    }
+
+    /// Iterate over the leaves whose key starts with the given prefix in key order.
+    pub fn iter_prefix(&self, prefix: impl AsRef<[L]>) -> Iter<T, L> {
+        let mut iter = self.iter();
+        iter.seek_prefix(&self.collation.normalize(prefix.as_ref()));
+        iter
+    }
+
+    /// Iterate over the leaves whose key is within the given range in key order.
+    pub fn range<K: AsRef<[L]>>(&self, range: impl RangeBounds<K>) -> Range<T, L> {
+        Range::new(
+            self.root.clone(),
+            self.collation.clone(),
+            normalize_bound(range.start_bound(), self.collation.as_ref()),
+            normalize_bound(range.end_bound(), self.collation.as_ref()),
+        )
+    }
+
+    /// Iterate over the leaves whose key is a prefix of the given key, from the shortest key to
+    /// the given key itself.
+    pub fn iter_path(&self, key: impl AsRef<[L]>) -> PathIterator<T, L> {
+        let path = self.collation.normalize(key.as_ref()).into_owned();
+        PathIterator::new(self.root.clone(), self.collation.clone(), path)
+    }
+
+    /// Resolve the value of the given key by merging the values of every key along its path,
+    /// from the shortest key to the given key itself. The merge function receives the value
+    /// inherited so far and the value of the more specific key, which should override it.
+    /// Returns `None` if no key along the path exists.
+    pub fn resolve(&self, key: impl AsRef<[L]>, mut merge: impl FnMut(T, &T) -> T) -> Option<T> {
+        self.iter_path(key).fold(None, |inherited, leaf| {
+            Some(match inherited {
+                Some(inherited) => merge(inherited, leaf.get_value()),
+                None => leaf.get_value().clone(),
+            })
+        })
+    }
+
+    /// Get the aggregate of all the values whose key starts with the given prefix.
+    /// Returns `None` if no key has the prefix or the tree has no aggregate.
+    pub fn aggregate_prefix(&self, prefix: impl AsRef<[L]>) -> Option<T> {
+        let search = self.collation.normalize(prefix.as_ref());
+        self.root.aggregate_prefix(&search, self.collation.as_ref())
+    }
+
+    /// Get the key and value of the smallest key in the tree according to its collation.
+    pub fn minimum(&self) -> Option<(Vec<L>, T)> {
+        self.root.minimum()
+    }
+
+    /// Get the key and value of the largest key in the tree according to its collation.
+    pub fn maximum(&self) -> Option<(Vec<L>, T)> {
+        self.root.maximum()
+    }
+
+    /// Create a new tree with the same keys, each value mapped by the given function in key order.
+    /// The new tree keeps the collation but not the aggregate, as it is defined over the old values.
+    pub fn map<U: NodeValue>(&self, mut f: impl FnMut(&T) -> U) -> Tree<U, L> {
+        Tree {
+            root: self.root.map(&mut f).into(),
+            size: self.size,
+            seq: 0,
+            collation: self.collation.clone(),
+            monoid: None,
+            lineage: Arc::default(),
+            arena: None,
+        }
+    }
+
+    /// Keep only the keys whose key and value match the given predicate, returning the new tree.
+    pub fn filter(self, pred: impl FnMut(&[L], &T) -> bool) -> Tree<T, L> {
+        let mut txn = self.start_transaction();
+        txn.retain(pred);
+        txn.commit()
+    }
+
+    /// Pack the tree into a read-only [`FrozenTree`], which is faster to query and can be thawed
+    /// back into a tree.
+    pub fn freeze(&self) -> FrozenTree<T, L> {
+        FrozenTree::new(&self.root, self.collation.clone(), self.monoid.clone())
+    }
+
+    /// Subscribe to the commits of the transactions derived from this tree and its later
+    /// versions, in commit order. The tree is the snapshot the published commits apply to, from
+    /// the entries whose parent is its [`Tree::seq`] on.
+    ///
+    /// Only the changes of the transactions started after subscribing are recorded. A transaction
+    /// already open when subscribing is not published, but the entries made on top of it name it
+    /// as their parent, so the subscriber can tell it missed a commit and take a new snapshot.
+    pub fn subscribe(&self) -> Subscriber<T, L> {
+        self.lineage.subscribe()
+    }
+
+    /// Get the sequence number of this version of the tree in its lineage, which is the sequence
+    /// number of the commit it results from. Versions with different keys or values never share
+    /// a sequence number, so the changefeed entries whose parent is this number apply to it.
+    pub fn seq(&self) -> u64 {
+        self.seq
     }
 
     /// Create a new transaction for the tree.
-    pub fn start_transaction(&self) -> Txn<T> {
-        let root = (*self.root).clone();
+    pub fn start_transaction(&self) -> Txn<T, L> {
+        // the root is copied on the first write, so its watch fires once the copy is committed
         Txn {
-            root: RwLock::new(root.into()),
+            root: self.root.clone(),
             size: self.size.into(),
-            writable: None,
+            id: transaction::next_txn_id(),
+            base_seq: self.seq,
+            cloned_nodes: 0,
+            collation: self.collation.clone(),
+            monoid: self.monoid.clone(),
+            changes: self.lineage.has_subscribers().then(ChangeSet::default),
+            track_mutations: false,
+            signals: Vec::new(),
+            lineage: self.lineage.clone(),
+            arena: self.arena.clone(),
+            placeholder: Arc::default(),
         }
     }
 
     /// Insert a key-value pair into the tree, returning the new tree and the old value if exists.
-    pub fn insert(self, key: &str, value: T) -> (Tree<T>, Option<T>) {
+    pub fn insert(self, key: impl AsRef<[L]>, value: T) -> (Tree<T, L>, Option<T>) {
         let mut txn = self.start_transaction();
         let old_value = txn.insert(key, value);
         let new_tree = txn.commit();
@@ -61,15 +303,75 @@ impl<T: NodeValue> Tree<T> {
     }
 
     /// Delete a key from the tree, returning the new tree and the old value if exists.
-    pub fn delete(self, key: &str) -> (Tree<T>, Option<T>) {
+    pub fn delete(self, key: impl AsRef<[L]>) -> (Tree<T, L>, Option<T>) {
         let mut txn = self.start_transaction();
         let old_value = txn.delete(key);
         let new_tree = txn.commit();
         (new_tree, old_value)
     }
 
+    /// Insert the given key only if it does not exist yet, returning the new tree and a boolean
+    /// indicating if the key was inserted. The tree is returned untouched otherwise.
+    pub fn insert_if_absent(self, key: impl AsRef<[L]>, value: T) -> (Tree<T, L>, bool) {
+        if self.get_ref(&key).is_some() {
+            return (self, false);
+        }
+        let (new_tree, _) = self.insert(key, value);
+        (new_tree, true)
+    }
+
+    /// Replace the value of the given key with `new` only if its current value equals `expected`,
+    /// returning the new tree and a boolean indicating if the value was swapped.
+    /// The tree is returned untouched otherwise.
+    pub fn compare_and_swap(self, key: impl AsRef<[L]>, expected: &T, new: T) -> (Tree<T, L>, bool)
+    where
+        T: PartialEq,
+    {
+        if !self
+            .get_ref(&key)
+            .is_some_and(|leaf| leaf.get_value() == expected)
+        {
+            return (self, false);
+        }
+        let (new_tree, _) = self.insert(key, new);
+        (new_tree, true)
+    }
+
+    /// Delete the given key only if its value matches the given predicate, returning the new tree
+    /// and the deleted value if any. The tree is returned untouched otherwise.
+    pub fn delete_if(
+        self,
+        key: impl AsRef<[L]>,
+        pred: impl FnOnce(&T) -> bool,
+    ) -> (Tree<T, L>, Option<T>) {
+        if !self
+            .get_ref(&key)
+            .is_some_and(|leaf| pred(leaf.get_value()))
+        {
+            return (self, None);
+        }
+        self.delete(key)
+    }
+
+    /// Update the given key with the value returned by the given function, which receives the
+    /// current value if any. Returning `None` deletes the key. Returns the new tree and the old
+    /// value if any, the tree is returned untouched when nothing changes.
+    pub fn update(
+        self,
+        key: impl AsRef<[L]>,
+        f: impl FnOnce(Option<&T>) -> Option<T>,
+    ) -> (Tree<T, L>, Option<T>) {
+        let old_leaf = self.get_ref(&key);
+        let old_value = old_leaf.as_ref().map(|leaf| leaf.get_value().clone());
+        match f(old_leaf.as_ref().map(|leaf| leaf.get_value())) {
+            Some(value) => (self.insert(key, value).0, old_value),
+            None if old_leaf.is_some() => self.delete(key),
+            None => (self, None),
+        }
+    }
+
     /// Delete all keys with the given prefix from the tree, returning the new tree and a boolean indicating if any keys were deleted.
-    pub fn delete_prefix(self, prefix: &str) -> (Tree<T>, bool) {
+    pub fn delete_prefix(self, prefix: impl AsRef<[L]>) -> (Tree<T, L>, bool) {
         let mut txn = self.start_transaction();
         let has_deleted = txn.delete_prefix(prefix);
         let new_tree = txn.commit();
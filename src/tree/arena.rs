@@ -0,0 +1,120 @@
+use std::{fmt, sync::Arc};
+
+use parking_lot::Mutex;
+
+use crate::{
+    node::{LeafNode, Node},
+    utils::{EdgeLabel, NodeValue},
+};
+
+/// Nodes released to an arena, emptied and owned by it.
+type Released<T, L> = Vec<Arc<Node<T, L>>>;
+
+/// A pool of nodes reused by the trees attached to it with [`Tree::with_arena`].
+///
+/// Transactions of those trees take the nodes they create or copy from the arena, and the last
+/// tree holding a subtree hands its nodes back to the arena once dropped. Released subtrees are
+/// taken apart at once, dropping their keys and values, while the nodes still shared with other
+/// trees are left to them. Trees built and thrown away over and over with the same arena thus
+/// barely allocate nodes at all.
+///
+/// The arena keeps the emptied nodes until they are reused or the arena is cleared, and cloning
+/// the arena shares the pool.
+///
+/// [`Tree::with_arena`]: crate::tree::Tree::with_arena
+pub struct NodeArena<T, L = u8>
+where
+    T: NodeValue,
+    L: EdgeLabel,
+{
+    released: Arc<Mutex<Released<T, L>>>,
+}
+
+impl<T: NodeValue, L: EdgeLabel> NodeArena<T, L> {
+    /// Create a new empty arena.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Drop every node released to the arena.
+    pub fn clear(&self) {
+        let released = std::mem::take(&mut *self.released.lock());
+        drop(released);
+    }
+
+    /// Hands the given subtrees back to the arena, taking apart the nodes not shared with any
+    /// tree so their leaves and values are dropped right away.
+    pub(crate) fn release(&self, nodes: impl IntoIterator<Item = Arc<Node<T, L>>>) {
+        let mut pending: Vec<_> = nodes.into_iter().collect();
+        let mut released = self.released.lock();
+        while let Some(mut node) = pending.pop() {
+            // a node still shared with another tree is left to it
+            if let Some(writable_node) = Arc::get_mut(&mut node) {
+                writable_node.release_into(&mut pending);
+                released.push(node);
+            }
+        }
+    }
+
+    /// Takes a released node, which is owned by the arena.
+    fn take(&self) -> Option<Arc<Node<T, L>>> {
+        self.released.lock().pop()
+    }
+
+    /// Returns a node with the given prefix and leaf node, reusing a released node if any.
+    pub(crate) fn new_node(
+        &self,
+        prefix: &[L],
+        leaf: Option<Arc<LeafNode<T, L>>>,
+    ) -> Arc<Node<T, L>> {
+        match self.take() {
+            Some(mut node) => {
+                Arc::get_mut(&mut node)
+                    .expect("a released node is owned by the arena")
+                    .recycle(prefix, leaf);
+                node
+            }
+            None => match leaf {
+                Some(leaf) => Arc::new(Node::with_leaf(prefix, leaf)),
+                None => Arc::new(Node::new(prefix, None)),
+            },
+        }
+    }
+
+    /// Returns a copy of the given node, reusing a released node if any.
+    pub(crate) fn copy_node(&self, node: &Node<T, L>) -> Arc<Node<T, L>> {
+        match self.take() {
+            Some(mut copy) => {
+                Arc::get_mut(&mut copy)
+                    .expect("a released node is owned by the arena")
+                    .clone_from(node);
+                copy
+            }
+            None => Arc::new(node.clone()),
+        }
+    }
+}
+
+impl<T: NodeValue, L: EdgeLabel> Default for NodeArena<T, L> {
+    fn default() -> Self {
+        Self {
+            released: Arc::default(),
+        }
+    }
+}
+
+impl<T: NodeValue, L: EdgeLabel> Clone for NodeArena<T, L> {
+    fn clone(&self) -> Self {
+        Self {
+            released: self.released.clone(),
+        }
+    }
+}
+
+impl<T: NodeValue, L: EdgeLabel> fmt::Debug for NodeArena<T, L> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NodeArena")
+            .field("released", &self.released.lock().len())
+            .finish()
+    }
+}
@@ -0,0 +1,121 @@
+use std::{collections::BTreeMap, sync::Arc};
+
+use crate::{
+    collation::Collation,
+    node::LeafNode,
+    utils::{EdgeLabel, NodeValue},
+};
+
+/// The kind of change made to a key by a transaction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ChangeKind {
+    Inserted,
+    Updated,
+    Deleted,
+}
+
+/// The change made to a single key, holding the leaf before and after the transaction.
+#[derive(Debug, Clone)]
+pub struct Change<T, L = u8>
+where
+    T: NodeValue,
+    L: EdgeLabel,
+{
+    old: Option<Arc<LeafNode<T, L>>>,
+    new: Option<Arc<LeafNode<T, L>>>,
+}
+
+impl<T: NodeValue, L: EdgeLabel> Change<T, L> {
+    /// Get the kind of the change.
+    pub fn kind(&self) -> ChangeKind {
+        match (&self.old, &self.new) {
+            (None, _) => ChangeKind::Inserted,
+            (Some(_), Some(_)) => ChangeKind::Updated,
+            (Some(_), None) => ChangeKind::Deleted,
+        }
+    }
+
+    /// Get the changed key, as it was last inserted.
+    pub fn key(&self) -> &[L] {
+        self.new
+            .as_ref()
+            .or(self.old.as_ref())
+            .expect("change should have a leaf")
+            .get_key()
+    }
+
+    /// Get the value of the key before the transaction if it existed.
+    pub fn old_value(&self) -> Option<&T> {
+        self.old.as_ref().map(|leaf| leaf.get_value())
+    }
+
+    /// Get the value of the key after the transaction if it still exists.
+    pub fn new_value(&self) -> Option<&T> {
+        self.new.as_ref().map(|leaf| leaf.get_value())
+    }
+}
+
+/// The keys changed by a transaction, in the order of their normalized keys.
+///
+/// Several changes to the same key are coalesced into a single change from the value before the
+/// transaction to the value after it. A key inserted then deleted does not appear at all.
+#[derive(Debug, Clone)]
+pub struct ChangeSet<T, L = u8>
+where
+    T: NodeValue,
+    L: EdgeLabel,
+{
+    changes: BTreeMap<Vec<L>, Change<T, L>>,
+}
+
+impl<T: NodeValue, L: EdgeLabel> Default for ChangeSet<T, L> {
+    fn default() -> Self {
+        Self {
+            changes: BTreeMap::new(),
+        }
+    }
+}
+
+impl<T: NodeValue, L: EdgeLabel> ChangeSet<T, L> {
+    /// Records a change of a key from the old leaf to the new leaf.
+    pub(crate) fn record(
+        &mut self,
+        collation: &dyn Collation<L>,
+        old: Option<Arc<LeafNode<T, L>>>,
+        new: Option<Arc<LeafNode<T, L>>>,
+    ) {
+        let Some(leaf) = new.as_ref().or(old.as_ref()) else {
+            return;
+        };
+        let key = collation.normalize(leaf.get_key()).into_owned();
+
+        let change = self
+            .changes
+            .entry(key.clone())
+            .or_insert(Change { old, new: None });
+        change.new = new;
+        if change.old.is_none() && change.new.is_none() {
+            self.changes.remove(&key);
+        }
+    }
+
+    /// Get the number of changed keys.
+    pub fn len(&self) -> usize {
+        self.changes.len()
+    }
+
+    /// Returns true if no key was changed.
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Iterate over the changes.
+    pub fn iter(&self) -> impl Iterator<Item = &Change<T, L>> {
+        self.changes.values()
+    }
+
+    /// Iterate over the changes of the given kind.
+    pub fn iter_kind(&self, kind: ChangeKind) -> impl Iterator<Item = &Change<T, L>> {
+        self.iter().filter(move |change| change.kind() == kind)
+    }
+}
@@ -0,0 +1,129 @@
+use std::sync::{
+    Arc,
+    mpsc::{self, Receiver, Sender},
+};
+
+use parking_lot::Mutex;
+
+use crate::{
+    tree::ChangeSet,
+    utils::{EdgeLabel, NodeValue},
+};
+
+/// A committed transaction published to the subscribers of a tree lineage.
+///
+/// Every version of a tree may be changed, so the commits of a lineage form a tree of versions
+/// rather than a sequence. An entry names the version it applies to with its parent sequence
+/// number: a consumer replicating a version applies the entries whose parent is that version, and
+/// detects forks and missed commits when the parent is a version it does not hold.
+pub struct ChangefeedEntry<T, L = u8>
+where
+    T: NodeValue,
+    L: EdgeLabel,
+{
+    seq: u64,
+    parent_seq: u64,
+    changes: ChangeSet<T, L>,
+}
+
+impl<T: NodeValue, L: EdgeLabel> ChangefeedEntry<T, L> {
+    /// Get the sequence number of the commit, increasing with every commit of the lineage.
+    pub fn seq(&self) -> u64 {
+        self.seq
+    }
+
+    /// Get the sequence number of the version the commit was made on, see [`Tree::seq`].
+    ///
+    /// [`Tree::seq`]: crate::Tree::seq
+    pub fn parent_seq(&self) -> u64 {
+        self.parent_seq
+    }
+
+    /// Get the keys changed by the commit.
+    pub fn changes(&self) -> &ChangeSet<T, L> {
+        &self.changes
+    }
+}
+
+/// The receiving end of a changefeed, yielding the commits in sequence order.
+pub type Subscriber<T, L = u8> = Receiver<Arc<ChangefeedEntry<T, L>>>;
+
+/// The lineage shared by every version of a tree derived through transactions.
+pub struct Lineage<T, L = u8>
+where
+    T: NodeValue,
+    L: EdgeLabel,
+{
+    state: Mutex<LineageState<T, L>>,
+}
+
+struct LineageState<T, L>
+where
+    T: NodeValue,
+    L: EdgeLabel,
+{
+    seq: u64,
+    subscribers: Vec<Sender<Arc<ChangefeedEntry<T, L>>>>,
+}
+
+impl<T: NodeValue, L: EdgeLabel> Default for Lineage<T, L> {
+    fn default() -> Self {
+        Self {
+            state: Mutex::new(LineageState {
+                seq: 0,
+                subscribers: Vec::new(),
+            }),
+        }
+    }
+}
+
+impl<T: NodeValue, L: EdgeLabel> std::fmt::Debug for Lineage<T, L> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let state = self.state.lock();
+        f.debug_struct("Lineage")
+            .field("seq", &state.seq)
+            .field("subscribers", &state.subscribers.len())
+            .finish()
+    }
+}
+
+impl<T: NodeValue, L: EdgeLabel> Lineage<T, L> {
+    /// Returns true if anyone subscribed to the lineage.
+    pub(crate) fn has_subscribers(&self) -> bool {
+        !self.state.lock().subscribers.is_empty()
+    }
+
+    /// Registers a new subscriber of the lineage.
+    pub(crate) fn subscribe(&self) -> Subscriber<T, L> {
+        let (sender, receiver) = mpsc::channel();
+        self.state.lock().subscribers.push(sender);
+        receiver
+    }
+
+    /// Assigns a sequence number to a commit made on the version with the given sequence number,
+    /// publishing its changes to the subscribers and dropping the disconnected ones. A commit known
+    /// to change nothing keeps the sequence number of its parent, while a commit whose changes were
+    /// not recorded still takes a new one, so the subscribers can tell it is missing.
+    pub(crate) fn commit(&self, parent_seq: u64, changes: Option<&ChangeSet<T, L>>) -> u64 {
+        if changes.is_some_and(ChangeSet::is_empty) {
+            return parent_seq;
+        }
+
+        let mut state = self.state.lock();
+        state.seq += 1;
+        let seq = state.seq;
+        if let Some(changes) = changes
+            && !state.subscribers.is_empty()
+        {
+            let entry = Arc::new(ChangefeedEntry {
+                seq,
+                parent_seq,
+                changes: changes.clone(),
+            });
+            state
+                .subscribers
+                .retain(|subscriber| subscriber.send(entry.clone()).is_ok());
+        }
+        seq
+    }
+}
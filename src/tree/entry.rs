@@ -0,0 +1,182 @@
+use std::sync::{Arc, atomic::Ordering};
+
+use crate::{
+    node::LeafNode,
+    tree::transaction::{Path, Txn},
+    utils::{EdgeLabel, NodeValue},
+};
+
+/// A view into a single key of a transaction, which is either occupied or vacant.
+///
+/// The entry is created from a single descent of the tree, and any modification made through it
+/// follows the path found by that descent instead of searching the key again.
+pub enum Entry<'a, T, L = u8>
+where
+    T: NodeValue,
+    L: EdgeLabel,
+{
+    Occupied(OccupiedEntry<'a, T, L>),
+    Vacant(VacantEntry<'a, T, L>),
+}
+
+impl<'a, T: NodeValue, L: EdgeLabel> Entry<'a, T, L> {
+    /// Get the key of the entry.
+    pub fn key(&self) -> &[L] {
+        match self {
+            Entry::Occupied(entry) => entry.key(),
+            Entry::Vacant(entry) => entry.key(),
+        }
+    }
+
+    /// Insert the given value if the entry is vacant, returning the leaf of the entry.
+    pub fn or_insert(self, value: T) -> Arc<LeafNode<T, L>> {
+        self.or_insert_with(|| value)
+    }
+
+    /// Insert the value returned by the given function if the entry is vacant,
+    /// returning the leaf of the entry.
+    pub fn or_insert_with(self, f: impl FnOnce() -> T) -> Arc<LeafNode<T, L>> {
+        match self {
+            Entry::Occupied(entry) => entry.into_leaf(),
+            Entry::Vacant(entry) => entry.insert(f()),
+        }
+    }
+
+    /// Modify the value in place if the entry is occupied.
+    pub fn and_modify(self, f: impl FnOnce(&mut T)) -> Self {
+        match self {
+            Entry::Occupied(mut entry) => {
+                let mut value = entry.get().clone();
+                f(&mut value);
+                entry.insert(value);
+                Entry::Occupied(entry)
+            }
+            Entry::Vacant(entry) => Entry::Vacant(entry),
+        }
+    }
+}
+
+/// An entry whose key exists in the transaction.
+pub struct OccupiedEntry<'a, T, L = u8>
+where
+    T: NodeValue,
+    L: EdgeLabel,
+{
+    txn: &'a mut Txn<T, L>,
+
+    // path to the node holding the key
+    path: Path,
+    leaf: Arc<LeafNode<T, L>>,
+}
+
+impl<'a, T: NodeValue, L: EdgeLabel> OccupiedEntry<'a, T, L> {
+    pub(super) fn new(txn: &'a mut Txn<T, L>, path: Path, leaf: Arc<LeafNode<T, L>>) -> Self {
+        Self { txn, path, leaf }
+    }
+
+    /// Get the key of the entry as it was inserted.
+    pub fn key(&self) -> &[L] {
+        self.leaf.get_key()
+    }
+
+    /// Get a reference to the value of the entry.
+    pub fn get(&self) -> &T {
+        self.leaf.get_value()
+    }
+
+    /// Get the leaf of the entry.
+    pub fn into_leaf(self) -> Arc<LeafNode<T, L>> {
+        self.leaf
+    }
+
+    /// Replace the value of the entry, returning the old value.
+    pub fn insert(&mut self, value: T) -> T {
+        let leaf = Arc::new(LeafNode::new(self.leaf.get_key(), value));
+        let (root, mut writer) = self.txn.writer();
+        writer.modify_path(root, &self.path, true, |writer, node, _| {
+            let writable_node = writer.writable_node(node);
+            writable_node.leaf.replace(leaf.clone());
+            writer.update_summary(writable_node);
+        });
+
+        let old_leaf = std::mem::replace(&mut self.leaf, leaf.clone());
+        let old_value = old_leaf.get_value().clone();
+        writer.record_change(Some(old_leaf), Some(leaf));
+        old_value
+    }
+
+    /// Remove the entry from the transaction, returning its value.
+    pub fn remove(self) -> T {
+        let OccupiedEntry { txn, path, leaf } = self;
+
+        let (root, mut writer) = txn.writer();
+        writer.modify_path(root, &path, true, |writer, node, is_root| {
+            writer.remove_leaf(node, is_root)
+        });
+        writer.record_change(Some(leaf.clone()), None);
+        txn.size.fetch_sub(1, Ordering::Relaxed);
+
+        leaf.get_value().clone()
+    }
+}
+
+/// An entry whose key does not exist in the transaction.
+pub struct VacantEntry<'a, T, L = u8>
+where
+    T: NodeValue,
+    L: EdgeLabel,
+{
+    txn: &'a mut Txn<T, L>,
+    key: Vec<L>,
+
+    // normalized key and the length of it matched by the nodes of the path
+    search: Vec<L>,
+    consumed: usize,
+
+    // path to the node where the descent stopped
+    path: Path,
+}
+
+impl<'a, T: NodeValue, L: EdgeLabel> VacantEntry<'a, T, L> {
+    pub(super) fn new(
+        txn: &'a mut Txn<T, L>,
+        key: Vec<L>,
+        search: Vec<L>,
+        path: Path,
+        consumed: usize,
+    ) -> Self {
+        Self {
+            txn,
+            key,
+            search,
+            consumed,
+            path,
+        }
+    }
+
+    /// Get the key of the entry.
+    pub fn key(&self) -> &[L] {
+        &self.key
+    }
+
+    /// Insert the given value into the entry, returning the new leaf.
+    pub fn insert(self, value: T) -> Arc<LeafNode<T, L>> {
+        let VacantEntry {
+            txn,
+            key,
+            search,
+            consumed,
+            path,
+        } = self;
+
+        let leaf = Arc::new(LeafNode::new(&key, value));
+        let (root, mut writer) = txn.writer();
+        writer.modify_path(root, &path, true, |writer, node, _| {
+            writer.internal_insert(node, &search[consumed..], leaf.clone())
+        });
+        writer.record_change(None, Some(leaf.clone()));
+        txn.size.fetch_add(1, Ordering::Relaxed);
+
+        leaf
+    }
+}
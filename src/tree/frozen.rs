@@ -0,0 +1,387 @@
+use std::{
+    cmp::Ordering,
+    ops::{Bound, RangeBounds},
+    sync::Arc,
+};
+
+use crate::{
+    aggregate::Monoid,
+    collation::Collation,
+    node::{Edge, LeafNode, Node},
+    tree::Tree,
+    utils::{EdgeLabel, NodeValue, normalize_bound},
+};
+
+/// Position in the arrays of a frozen tree.
+type Index = u32;
+
+/// Converts a position in the arrays of a frozen tree to an index.
+fn index(position: usize) -> Index {
+    Index::try_from(position).expect("frozen tree arrays are indexed by u32")
+}
+
+/// A node of a frozen tree, referring to ranges of the arrays of the tree.
+#[derive(Debug, Clone, Copy)]
+struct FrozenNode {
+    // range of the prefix in the prefix labels
+    prefix_start: Index,
+    prefix_end: Index,
+
+    // range of the edges in the edge labels and children
+    edges_start: Index,
+    edges_end: Index,
+
+    // range of the leaves of the subtree in key order, starting with the leaf of the node if any
+    leaves_start: Index,
+    leaves_end: Index,
+    has_leaf: bool,
+}
+
+/// Read-only radix tree packed into contiguous arrays, created with [`Tree::freeze`].
+///
+/// Nodes are laid out next to each other in depth-first order, with the edge labels, prefixes,
+/// keys and values in arrays of their own, so lookups walk a few compact arrays instead of
+/// chasing pointers. The leaves of every subtree are contiguous, which makes prefix and range
+/// scans a slice of the leaves. Use [`FrozenTree::thaw`] to modify the tree again.
+#[derive(Debug, Clone)]
+pub struct FrozenTree<T, L = u8>
+where
+    T: NodeValue,
+    L: EdgeLabel,
+{
+    // the root is the first node
+    nodes: Vec<FrozenNode>,
+    prefixes: Vec<L>,
+
+    // label and child node of every edge, the edges of a node are sorted by label
+    labels: Vec<L>,
+    children: Vec<Index>,
+
+    // keys as they were inserted and values of the leaves, in key order
+    keys: Vec<L>,
+    key_offsets: Vec<Index>,
+    values: Vec<T>,
+
+    collation: Arc<dyn Collation<L>>,
+    monoid: Option<Arc<dyn Monoid<T>>>,
+}
+
+impl<T: NodeValue, L: EdgeLabel> FrozenTree<T, L> {
+    /// Packs the tree under the given root.
+    pub(super) fn new(
+        root: &Node<T, L>,
+        collation: Arc<dyn Collation<L>>,
+        monoid: Option<Arc<dyn Monoid<T>>>,
+    ) -> Self {
+        let mut frozen = Self {
+            nodes: Vec::new(),
+            prefixes: Vec::new(),
+            labels: Vec::new(),
+            children: Vec::new(),
+            keys: Vec::new(),
+            key_offsets: vec![0],
+            values: Vec::new(),
+            collation,
+            monoid,
+        };
+
+        // nodes are visited in depth-first order, with the edge leading to them
+        let mut stack = vec![(root, None)];
+        while let Some((node, parent_edge)) = stack.pop() {
+            if let Some(edge) = parent_edge {
+                frozen.children[edge] = index(frozen.nodes.len());
+            }
+
+            let prefix_start = index(frozen.prefixes.len());
+            frozen.prefixes.extend_from_slice(&node.prefix);
+
+            let leaves_start = index(frozen.values.len());
+            if let Some(leaf) = node.leaf.as_ref() {
+                frozen.keys.extend_from_slice(leaf.get_key());
+                frozen.key_offsets.push(index(frozen.keys.len()));
+                frozen.values.push(leaf.get_value().clone());
+            }
+
+            // children are pushed in reverse to be visited in key order
+            let edges_start = frozen.labels.len();
+            for edge in node.edges() {
+                frozen.labels.push(edge.get_label().clone());
+                frozen.children.push(0);
+            }
+            stack.extend(
+                node.edges()
+                    .iter()
+                    .enumerate()
+                    .rev()
+                    .map(|(edge_idx, edge)| (edge.get_node(), Some(edges_start + edge_idx))),
+            );
+
+            frozen.nodes.push(FrozenNode {
+                prefix_start,
+                prefix_end: index(frozen.prefixes.len()),
+                edges_start: index(edges_start),
+                edges_end: index(frozen.labels.len()),
+                leaves_start,
+                leaves_end: leaves_start,
+                has_leaf: node.leaf.is_some(),
+            });
+        }
+
+        // children come after their parent, and the leaves of a subtree end with its last child
+        for node_idx in (0..frozen.nodes.len()).rev() {
+            let node = frozen.nodes[node_idx];
+            frozen.nodes[node_idx].leaves_end = if node.edges_start < node.edges_end {
+                let last_child = frozen.children[node.edges_end as usize - 1];
+                frozen.nodes[last_child as usize].leaves_end
+            } else {
+                node.leaves_start + Index::from(node.has_leaf)
+            };
+        }
+        frozen
+    }
+
+    /// Get the number of keys in the tree.
+    pub fn len(&self) -> u32 {
+        index(self.values.len())
+    }
+
+    /// Returns true if the tree contains no keys.
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Get a reference to the value associated with the given key if exists.
+    pub fn get(&self, key: impl AsRef<[L]>) -> Option<&T> {
+        let search = self.collation.normalize(key.as_ref());
+        let node = self.find_node(&search)?;
+        node.has_leaf
+            .then(|| &self.values[node.leaves_start as usize])
+    }
+
+    /// Get the key and value of the longest key which is a prefix of the given key.
+    pub fn longest_prefix(&self, key: impl AsRef<[L]>) -> Option<(&[L], &T)> {
+        let search = self.collation.normalize(key.as_ref());
+        let mut search = search.as_ref();
+        let mut node = &self.nodes[0];
+        let mut last = None;
+        loop {
+            if node.has_leaf {
+                last = Some(node.leaves_start as usize);
+            }
+
+            let Some(label) = search.first() else {
+                break;
+            };
+            let Ok(edge) = self.search_edge(node, label) else {
+                break;
+            };
+            let child = self.child(edge);
+            let Some(rest) = search.strip_prefix(self.prefix(child)) else {
+                break;
+            };
+            search = rest;
+            node = child;
+        }
+        last.map(|leaf| self.leaf(leaf))
+    }
+
+    /// Iterate over all the keys and values of the tree in key order.
+    pub fn iter(&self) -> FrozenIter<'_, T, L> {
+        self.leaves(0..self.values.len())
+    }
+
+    /// Iterate over the keys and values whose key starts with the given prefix in key order.
+    pub fn iter_prefix(&self, prefix: impl AsRef<[L]>) -> FrozenIter<'_, T, L> {
+        let search = self.collation.normalize(prefix.as_ref());
+        match self.find_prefix(&search) {
+            Some(node) => self.leaves(node.leaves_start as usize..node.leaves_end as usize),
+            None => self.leaves(0..0),
+        }
+    }
+
+    /// Iterate over the keys and values whose key is within the given range in key order.
+    pub fn range<K: AsRef<[L]>>(&self, range: impl RangeBounds<K>) -> FrozenIter<'_, T, L> {
+        let start = match normalize_bound(range.start_bound(), self.collation.as_ref()) {
+            Bound::Included(key) => self.lower_bound(&key),
+            Bound::Excluded(key) => self.upper_bound(&key),
+            Bound::Unbounded => 0,
+        };
+        let end = match normalize_bound(range.end_bound(), self.collation.as_ref()) {
+            Bound::Included(key) => self.upper_bound(&key),
+            Bound::Excluded(key) => self.lower_bound(&key),
+            Bound::Unbounded => self.values.len(),
+        };
+        self.leaves(start..end.max(start))
+    }
+
+    /// Unpack the frozen tree into a tree which can be modified again, with the same collation
+    /// and aggregate.
+    pub fn thaw(&self) -> Tree<T, L> {
+        // children come after their parent, so building the nodes backwards builds them first
+        let mut nodes: Vec<Option<Arc<Node<T, L>>>> = vec![None; self.nodes.len()];
+        for node_idx in (0..self.nodes.len()).rev() {
+            let frozen_node = &self.nodes[node_idx];
+            let leaf = frozen_node.has_leaf.then(|| {
+                let (key, value) = self.leaf(frozen_node.leaves_start as usize);
+                LeafNode::new(key, value.clone())
+            });
+            let edges = (frozen_node.edges_start as usize..frozen_node.edges_end as usize)
+                .map(|edge| {
+                    let child = nodes[self.children[edge] as usize].take();
+                    Edge::new(
+                        self.labels[edge].clone(),
+                        child.expect("children are built before their parent"),
+                    )
+                })
+                .collect();
+
+            let mut node = Node::new_with_edges(self.prefix(frozen_node), leaf, edges);
+            if let Some(monoid) = self.monoid.as_deref() {
+                node.update_summary(monoid);
+            }
+            nodes[node_idx] = Some(node.into());
+        }
+
+        Tree {
+            root: nodes[0].take().expect("a frozen tree has a root"),
+            size: self.len(),
+            seq: 0,
+            collation: self.collation.clone(),
+            monoid: self.monoid.clone(),
+            lineage: Arc::default(),
+            arena: None,
+        }
+    }
+
+    /// Returns the prefix of the node.
+    fn prefix(&self, node: &FrozenNode) -> &[L] {
+        &self.prefixes[node.prefix_start as usize..node.prefix_end as usize]
+    }
+
+    /// Returns the child node of the edge.
+    fn child(&self, edge: usize) -> &FrozenNode {
+        &self.nodes[self.children[edge] as usize]
+    }
+
+    /// Returns the key and value of the leaf at the given position in key order.
+    fn leaf(&self, leaf: usize) -> (&[L], &T) {
+        let key = self.key_offsets[leaf] as usize..self.key_offsets[leaf + 1] as usize;
+        (&self.keys[key], &self.values[leaf])
+    }
+
+    /// Returns an iterator over the leaves at the given positions.
+    fn leaves(&self, leaves: std::ops::Range<usize>) -> FrozenIter<'_, T, L> {
+        FrozenIter { tree: self, leaves }
+    }
+
+    /// Returns the edge of the node with the given label, or where it would be inserted.
+    fn search_edge(&self, node: &FrozenNode, label: &L) -> Result<usize, usize> {
+        let edges_start = node.edges_start as usize;
+        self.labels[edges_start..node.edges_end as usize]
+            .binary_search_by(|edge_label| self.collation.compare(edge_label, label))
+            .map(|edge_idx| edges_start + edge_idx)
+            .map_err(|edge_idx| edges_start + edge_idx)
+    }
+
+    /// Returns the node of the given (normalized) key.
+    fn find_node(&self, key: &[L]) -> Option<&FrozenNode> {
+        let mut search = key;
+        let mut node = &self.nodes[0];
+        while let Some(label) = search.first() {
+            let child = self.child(self.search_edge(node, label).ok()?);
+            search = search.strip_prefix(self.prefix(child))?;
+            node = child;
+        }
+        Some(node)
+    }
+
+    /// Returns the node covering every key with the given (normalized) prefix.
+    fn find_prefix(&self, prefix: &[L]) -> Option<&FrozenNode> {
+        let mut search = prefix;
+        let mut node = &self.nodes[0];
+        while let Some(label) = search.first() {
+            let child = self.child(self.search_edge(node, label).ok()?);
+            let child_prefix = self.prefix(child);
+            if child_prefix.starts_with(search) {
+                return Some(child);
+            }
+            search = search.strip_prefix(child_prefix)?;
+            node = child;
+        }
+        Some(node)
+    }
+
+    /// Returns the position of the first leaf whose (normalized) key is not less than the given key.
+    fn lower_bound(&self, key: &[L]) -> usize {
+        let mut search = key;
+        let mut node = &self.nodes[0];
+        while let Some(label) = search.first() {
+            // the key of the node itself is a shorter prefix of the key, which sorts before it
+            let edge = match self.search_edge(node, label) {
+                Ok(edge) => edge,
+                Err(edge) if edge < node.edges_end as usize => {
+                    return self.child(edge).leaves_start as usize;
+                }
+                Err(_) => return node.leaves_end as usize,
+            };
+
+            let child = self.child(edge);
+            let child_prefix = self.prefix(child);
+            let len = child_prefix.len().min(search.len());
+            match self
+                .collation
+                .compare_keys(&child_prefix[..len], &search[..len])
+            {
+                Ordering::Less => return child.leaves_end as usize,
+                Ordering::Greater => return child.leaves_start as usize,
+                Ordering::Equal if len == search.len() => return child.leaves_start as usize,
+                Ordering::Equal => {
+                    search = &search[len..];
+                    node = child;
+                }
+            }
+        }
+        node.leaves_start as usize
+    }
+
+    /// Returns the position after the leaves whose (normalized) key is not greater than the given
+    /// key.
+    fn upper_bound(&self, key: &[L]) -> usize {
+        let position = self.lower_bound(key);
+        match self.find_node(key) {
+            Some(node) if node.has_leaf => position + 1,
+            _ => position,
+        }
+    }
+}
+
+/// Iterator over the keys and values of a [`FrozenTree`] in key order.
+#[derive(Debug, Clone)]
+pub struct FrozenIter<'a, T, L = u8>
+where
+    T: NodeValue,
+    L: EdgeLabel,
+{
+    tree: &'a FrozenTree<T, L>,
+    leaves: std::ops::Range<usize>,
+}
+
+impl<'a, T: NodeValue, L: EdgeLabel> Iterator for FrozenIter<'a, T, L> {
+    type Item = (&'a [L], &'a T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.leaves.next().map(|leaf| self.tree.leaf(leaf))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.leaves.size_hint()
+    }
+}
+
+impl<T: NodeValue, L: EdgeLabel> DoubleEndedIterator for FrozenIter<'_, T, L> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.leaves.next_back().map(|leaf| self.tree.leaf(leaf))
+    }
+}
+
+impl<T: NodeValue, L: EdgeLabel> ExactSizeIterator for FrozenIter<'_, T, L> {}
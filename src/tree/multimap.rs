@@ -0,0 +1,495 @@
+use std::{
+    cmp::Ordering,
+    fmt,
+    hash::{Hash, Hasher},
+    sync::Arc,
+};
+
+use crate::{
+    collation::{Binary, Collation},
+    node::Iter,
+    tree::{Entry, Tree, transaction::Txn},
+    utils::{EdgeLabel, NodeValue},
+};
+
+/// The ordered collection of values held by a key of a [`MultiTree`].
+///
+/// Values are kept sorted and unique in a persistent balanced tree shared between tree versions,
+/// so reading it never clones the values. Adding or removing a value copies the O(log k) nodes on
+/// the path to it only, while the rest of the collection stays shared with the old version.
+pub struct Values<T>(Link<T>);
+
+/// A subtree of the values, empty if none.
+type Link<T> = Option<Arc<ValueNode<T>>>;
+
+/// A node of the balanced tree of values, which is never modified once created.
+struct ValueNode<T> {
+    value: T,
+    left: Link<T>,
+    right: Link<T>,
+
+    // height of the subtree to balance it, and number of values in it to index them
+    height: u8,
+    len: usize,
+}
+
+impl<T> ValueNode<T> {
+    fn new(value: T, left: Link<T>, right: Link<T>) -> Arc<Self> {
+        Arc::new(Self {
+            height: height(&left).max(height(&right)) + 1,
+            len: len(&left) + len(&right) + 1,
+            value,
+            left,
+            right,
+        })
+    }
+}
+
+fn height<T>(link: &Link<T>) -> u8 {
+    link.as_ref().map_or(0, |node| node.height)
+}
+
+fn len<T>(link: &Link<T>) -> usize {
+    link.as_ref().map_or(0, |node| node.len)
+}
+
+/// Creates a node with the given value and subtrees, whose heights differ by two at most,
+/// rotating them so the heights of the subtrees of the new node differ by one at most.
+fn balance<T: Clone>(value: T, left: Link<T>, right: Link<T>) -> Arc<ValueNode<T>> {
+    if height(&left) > height(&right) + 1 {
+        let left = left.expect("a higher subtree is not empty");
+        if height(&left.left) >= height(&left.right) {
+            let right = ValueNode::new(value, left.right.clone(), right);
+            return ValueNode::new(left.value.clone(), left.left.clone(), Some(right));
+        }
+        let inner = left.right.as_ref().expect("a higher subtree is not empty");
+        let new_left = ValueNode::new(left.value.clone(), left.left.clone(), inner.left.clone());
+        let new_right = ValueNode::new(value, inner.right.clone(), right);
+        return ValueNode::new(inner.value.clone(), Some(new_left), Some(new_right));
+    }
+    if height(&right) > height(&left) + 1 {
+        let right = right.expect("a higher subtree is not empty");
+        if height(&right.right) >= height(&right.left) {
+            let left = ValueNode::new(value, left, right.left.clone());
+            return ValueNode::new(right.value.clone(), Some(left), right.right.clone());
+        }
+        let inner = right.left.as_ref().expect("a higher subtree is not empty");
+        let new_left = ValueNode::new(value, left, inner.left.clone());
+        let new_right = ValueNode::new(
+            right.value.clone(),
+            inner.right.clone(),
+            right.right.clone(),
+        );
+        return ValueNode::new(inner.value.clone(), Some(new_left), Some(new_right));
+    }
+    ValueNode::new(value, left, right)
+}
+
+/// Returns the subtree with the given value added, or `None` if it already holds the value.
+fn insert<T: Clone + Ord>(link: &Link<T>, value: T) -> Option<Arc<ValueNode<T>>> {
+    let Some(node) = link else {
+        return Some(ValueNode::new(value, None, None));
+    };
+    match value.cmp(&node.value) {
+        Ordering::Less => {
+            let left = insert(&node.left, value)?;
+            Some(balance(node.value.clone(), Some(left), node.right.clone()))
+        }
+        Ordering::Greater => {
+            let right = insert(&node.right, value)?;
+            Some(balance(node.value.clone(), node.left.clone(), Some(right)))
+        }
+        Ordering::Equal => None,
+    }
+}
+
+/// Returns the subtree with the given value removed, or `None` if it does not hold the value.
+fn remove<T: Clone + Ord>(link: &Link<T>, value: &T) -> Option<Link<T>> {
+    let node = link.as_ref()?;
+    match value.cmp(&node.value) {
+        Ordering::Less => {
+            let left = remove(&node.left, value)?;
+            Some(Some(balance(node.value.clone(), left, node.right.clone())))
+        }
+        Ordering::Greater => {
+            let right = remove(&node.right, value)?;
+            Some(Some(balance(node.value.clone(), node.left.clone(), right)))
+        }
+        Ordering::Equal => Some(match (&node.left, &node.right) {
+            (None, right) => right.clone(),
+            (left, None) => left.clone(),
+            (left, Some(right)) => {
+                let (min, right) = remove_min(right);
+                Some(balance(min, left.clone(), right))
+            }
+        }),
+    }
+}
+
+/// Returns the smallest value of the subtree and the subtree without it.
+fn remove_min<T: Clone>(node: &ValueNode<T>) -> (T, Link<T>) {
+    match &node.left {
+        None => (node.value.clone(), node.right.clone()),
+        Some(left) => {
+            let (min, left) = remove_min(left);
+            let node = balance(node.value.clone(), left, node.right.clone());
+            (min, Some(node))
+        }
+    }
+}
+
+impl<T: NodeValue + Ord> Values<T> {
+    /// Returns a copy of the values with the given value added, or `None` if it already exists.
+    fn with_value(&self, value: T) -> Option<Self> {
+        insert(&self.0, value).map(|node| Self(Some(node)))
+    }
+
+    /// Returns a copy of the values with the given value removed, or `None` if it does not exist.
+    fn without_value(&self, value: &T) -> Option<Self> {
+        remove(&self.0, value).map(Self)
+    }
+
+    /// Returns true if the collection holds the given value.
+    pub fn contains(&self, value: &T) -> bool {
+        let mut link = &self.0;
+        while let Some(node) = link {
+            link = match value.cmp(&node.value) {
+                Ordering::Less => &node.left,
+                Ordering::Greater => &node.right,
+                Ordering::Equal => return true,
+            };
+        }
+        false
+    }
+}
+
+impl<T> Values<T> {
+    /// Get the number of values.
+    pub fn len(&self) -> usize {
+        len(&self.0)
+    }
+
+    /// Returns true if the collection holds no values.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_none()
+    }
+
+    /// Get the value at the given index in value order.
+    pub fn get(&self, mut idx: usize) -> Option<&T> {
+        let mut link = &self.0;
+        while let Some(node) = link {
+            let left_len = len(&node.left);
+            link = match idx.cmp(&left_len) {
+                Ordering::Less => &node.left,
+                Ordering::Equal => return Some(&node.value),
+                Ordering::Greater => {
+                    idx -= left_len + 1;
+                    &node.right
+                }
+            };
+        }
+        None
+    }
+
+    /// Iterate over the values in order.
+    pub fn iter(&self) -> ValuesIter<'_, T> {
+        let mut iter = ValuesIter {
+            stack: Vec::new(),
+            remaining: self.len(),
+        };
+        iter.push_left(&self.0);
+        iter
+    }
+
+    /// Copies the values into a vector in order.
+    pub fn to_vec(&self) -> Vec<T>
+    where
+        T: Clone,
+    {
+        self.iter().cloned().collect()
+    }
+}
+
+impl<T> Default for Values<T> {
+    fn default() -> Self {
+        Self(None)
+    }
+}
+
+impl<T> Clone for Values<T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Values<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<T: PartialEq> PartialEq for Values<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().eq(other.iter())
+    }
+}
+
+impl<T: Eq> Eq for Values<T> {}
+
+impl<T: Hash> Hash for Values<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_usize(self.len());
+        self.iter().for_each(|value| value.hash(state));
+    }
+}
+
+impl<'a, T> IntoIterator for &'a Values<T> {
+    type IntoIter = ValuesIter<'a, T>;
+    type Item = &'a T;
+
+    fn into_iter(self) -> ValuesIter<'a, T> {
+        self.iter()
+    }
+}
+
+/// Iterates over the [`Values`] of a key in order.
+pub struct ValuesIter<'a, T> {
+    // nodes whose value and right subtree are left to visit, the next one on top
+    stack: Vec<&'a ValueNode<T>>,
+    remaining: usize,
+}
+
+impl<'a, T> ValuesIter<'a, T> {
+    fn push_left(&mut self, mut link: &'a Link<T>) {
+        while let Some(node) = link {
+            self.stack.push(node);
+            link = &node.left;
+        }
+    }
+}
+
+impl<'a, T> Iterator for ValuesIter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        let node = self.stack.pop()?;
+        self.push_left(&node.right);
+        self.remaining -= 1;
+        Some(&node.value)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<T> ExactSizeIterator for ValuesIter<'_, T> {}
+
+/// Immutable radix tree where each key holds an ordered collection of values.
+///
+/// It has the same copy-on-write semantics as [`Tree`], the keys are stored in a [`Tree`] whose
+/// leaves hold the [`Values`] of the key. A key exists as long as it has at least one value.
+#[derive(Debug)]
+pub struct MultiTree<T, L = u8>
+where
+    T: NodeValue + Ord,
+    L: EdgeLabel,
+{
+    tree: Tree<Values<T>, L>,
+}
+
+impl<T: NodeValue + Ord, L: EdgeLabel> Default for MultiTree<T, L> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: NodeValue + Ord, L: EdgeLabel> MultiTree<T, L> {
+    /// Create a new empty multimap tree.
+    pub fn new() -> Self {
+        Self::with_collation(Binary)
+    }
+
+    /// Create a new empty multimap tree which matches and orders its keys with the given collation.
+    pub fn with_collation(collation: impl Collation<L> + 'static) -> Self {
+        Self {
+            tree: Tree::with_collation(collation),
+        }
+    }
+
+    /// Get the number of keys in the tree.
+    pub fn len(&self) -> u32 {
+        self.tree.len()
+    }
+
+    /// Returns true if the tree contains no keys.
+    pub fn is_empty(&self) -> bool {
+        self.tree.is_empty()
+    }
+
+    /// Get the underlying tree mapping each key to its values.
+    pub fn as_tree(&self) -> &Tree<Values<T>, L> {
+        &self.tree
+    }
+
+    /// Get all the values of the given key, the collection is empty if the key does not exist.
+    pub fn get_all(&self, key: impl AsRef<[L]>) -> Values<T> {
+        self.tree.get(key).unwrap_or_default()
+    }
+
+    /// Iterate over all the (key, value) pairs of the tree in key then value order.
+    pub fn iter(&self) -> Pairs<T, L> {
+        Pairs::new(self.tree.iter())
+    }
+
+    /// Iterate over the (key, value) pairs whose key starts with the given prefix.
+    pub fn iter_prefix(&self, prefix: impl AsRef<[L]>) -> Pairs<T, L> {
+        Pairs::new(self.tree.iter_prefix(prefix))
+    }
+
+    /// Create a new transaction for the tree.
+    pub fn start_transaction(&self) -> MultiTxn<T, L> {
+        MultiTxn {
+            txn: self.tree.start_transaction(),
+        }
+    }
+
+    /// Add a value to the given key, returning the new tree and a boolean indicating if the value
+    /// was added. The tree is returned untouched if the key already holds the value.
+    pub fn insert_value(self, key: impl AsRef<[L]>, value: T) -> (MultiTree<T, L>, bool) {
+        if self.tree.with_value(&key, |values| values.contains(&value)) == Some(true) {
+            return (self, false);
+        }
+        let mut txn = self.start_transaction();
+        let inserted = txn.insert_value(key, value);
+        (txn.commit(), inserted)
+    }
+
+    /// Remove a value from the given key, returning the new tree and a boolean indicating if the
+    /// value was removed. The key is removed with its last value.
+    pub fn remove_value(self, key: impl AsRef<[L]>, value: &T) -> (MultiTree<T, L>, bool) {
+        if self.tree.with_value(&key, |values| values.contains(value)) != Some(true) {
+            return (self, false);
+        }
+        let mut txn = self.start_transaction();
+        let removed = txn.remove_value(key, value);
+        (txn.commit(), removed)
+    }
+}
+
+/// Transaction over a [`MultiTree`].
+pub struct MultiTxn<T, L = u8>
+where
+    T: NodeValue + Ord,
+    L: EdgeLabel,
+{
+    txn: Txn<Values<T>, L>,
+}
+
+impl<T: NodeValue + Ord, L: EdgeLabel> MultiTxn<T, L> {
+    /// Get the number of keys in the transaction.
+    pub fn len(&self) -> u32 {
+        self.txn.len()
+    }
+
+    /// Returns true if the transaction contains no keys.
+    pub fn is_empty(&self) -> bool {
+        self.txn.len() == 0
+    }
+
+    /// Get all the values of the given key, the collection is empty if the key does not exist.
+    pub fn get_all(&self, key: impl AsRef<[L]>) -> Values<T> {
+        self.txn.get(key).unwrap_or_default()
+    }
+
+    /// Add a value to the given key. Returns true if the value was added,
+    /// no node is copied if the key already holds the value.
+    pub fn insert_value(&mut self, key: impl AsRef<[L]>, value: T) -> bool {
+        match self.txn.entry(key) {
+            Entry::Occupied(mut entry) => match entry.get().with_value(value) {
+                Some(values) => {
+                    entry.insert(values);
+                    true
+                }
+                None => false,
+            },
+            Entry::Vacant(entry) => {
+                entry.insert(Values(Some(ValueNode::new(value, None, None))));
+                true
+            }
+        }
+    }
+
+    /// Remove a value from the given key, the key is removed with its last value.
+    /// Returns true if the value was removed.
+    pub fn remove_value(&mut self, key: impl AsRef<[L]>, value: &T) -> bool {
+        let Entry::Occupied(mut entry) = self.txn.entry(key) else {
+            return false;
+        };
+        match entry.get().without_value(value) {
+            Some(values) if values.is_empty() => {
+                entry.remove();
+                true
+            }
+            Some(values) => {
+                entry.insert(values);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Finalizes the transaction and returns the new tree.
+    pub fn commit(self) -> MultiTree<T, L> {
+        MultiTree {
+            tree: self.txn.commit(),
+        }
+    }
+}
+
+/// Iterates over the (key, value) pairs of a [`MultiTree`], flattening the values of each key.
+/// The pairs of a key share its key, which is copied once per key.
+pub struct Pairs<T, L = u8>
+where
+    T: NodeValue + Ord,
+    L: EdgeLabel,
+{
+    iter: Iter<Values<T>, L>,
+
+    // key being flattened, and its value nodes whose value and right subtree are left to visit
+    key: Arc<[L]>,
+    stack: Vec<Arc<ValueNode<T>>>,
+}
+
+impl<T: NodeValue + Ord, L: EdgeLabel> Pairs<T, L> {
+    fn new(iter: Iter<Values<T>, L>) -> Self {
+        Self {
+            iter,
+            key: Arc::new([]),
+            stack: Vec::new(),
+        }
+    }
+
+    fn push_left(&mut self, mut link: &Link<T>) {
+        while let Some(node) = link {
+            self.stack.push(node.clone());
+            link = &node.left;
+        }
+    }
+}
+
+impl<T: NodeValue + Ord, L: EdgeLabel> Iterator for Pairs<T, L> {
+    type Item = (Arc<[L]>, T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(node) = self.stack.pop() {
+                self.push_left(&node.right);
+                return Some((self.key.clone(), node.value.clone()));
+            }
+            let leaf = self.iter.next()?;
+            self.key = leaf.get_key().into();
+            self.push_left(&leaf.get_value().0);
+        }
+    }
+}
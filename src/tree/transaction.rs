@@ -1,282 +1,635 @@
-use std::{
-    num::NonZeroUsize,
-    sync::{
-        Arc,
-        atomic::{self, AtomicU32, Ordering},
-    },
+use std::sync::{
+    Arc,
+    atomic::{self, AtomicU32, AtomicU64, Ordering},
 };
 
-use lru::LruCache;
-use parking_lot::RwLock;
-
 use crate::{
-    NodeValue,
+    EdgeLabel, NodeValue,
+    aggregate::Monoid,
+    collation::Collation,
     node::{Edge, LeafNode, Node},
-    tree::Tree,
+    tree::{
+        ChangeSet, Lineage, NodeArena, Tree,
+        entry::{Entry, OccupiedEntry, VacantEntry},
+    },
     utils::longest_prefix,
+    watch::Watch,
 };
 
-const DEFAULT_MODIFIED_CACHE_SIZE: NonZeroUsize = NonZeroUsize::new(8192).unwrap();
+/// Source of the identifiers of the transactions.
+static NEXT_TXN_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Returns a new identifier for a transaction.
+pub(super) fn next_txn_id() -> u64 {
+    NEXT_TXN_ID.fetch_add(1, Ordering::Relaxed)
+}
 
-pub struct Txn<T>
+/// A snapshot of a transaction which it can be rolled back to.
+pub struct Savepoint<T, L = u8>
 where
     T: NodeValue,
+    L: EdgeLabel,
 {
-    // root is the modified root node of the tree
-    // TODO: maybe don't need RwLock here
-    pub root: RwLock<Arc<Node<T>>>,
+    // identifier of the transaction the savepoint was taken from
+    txn_id: u64,
+    root: Arc<Node<T, L>>,
+    size: u32,
+    changes: Option<ChangeSet<T, L>>,
+    signals_len: usize,
+}
+
+/// A signal fired on commit: the watch of a replaced node or leaf if anyone asked for it, or else
+/// the node or leaf itself, so a watch taken from it until the commit still fires.
+#[derive(Clone)]
+pub(super) enum PendingSignal<T, L>
+where
+    T: NodeValue,
+    L: EdgeLabel,
+{
+    Watch(Watch),
+    Node(Arc<Node<T, L>>),
+    Leaf(Arc<LeafNode<T, L>>),
+}
+
+impl<T: NodeValue, L: EdgeLabel> PendingSignal<T, L> {
+    fn fire(&self) {
+        match self {
+            PendingSignal::Watch(watch) => watch.fire(),
+            PendingSignal::Node(node) => node.signal.fire(),
+            PendingSignal::Leaf(leaf) => leaf.signal().fire(),
+        }
+    }
+}
+
+/// The indexes of the edges taken from the root during a descent.
+pub(super) type Path = Vec<usize>;
+
+pub struct Txn<T, L = u8>
+where
+    T: NodeValue,
+    L: EdgeLabel,
+{
+    // root is the modified root node of the tree, the nodes only reachable from it
+    // are owned by the transaction and modified in place
+    pub root: Arc<Node<T, L>>,
 
     // size tracks the size of tree as it is modified during the transaction
     pub size: AtomicU32,
 
-    // writable is a cache of nodes created during the transaction.
-    pub writable: Option<LruCache<Arc<Node<T>>, ()>>,
+    // id identifies the transaction, the savepoints of other transactions are rejected
+    pub(super) id: u64,
+
+    // base_seq is the sequence number of the version the transaction started from
+    pub(super) base_seq: u64,
+
+    // cloned_nodes counts the nodes copied during the transaction
+    pub(super) cloned_nodes: usize,
+
+    // collation is used to normalize the keys and order the edges
+    pub(super) collation: Arc<dyn Collation<L>>,
+
+    // monoid is used to maintain the aggregate of the modified nodes
+    pub(super) monoid: Option<Arc<dyn Monoid<T>>>,
+
+    // changes records the changed keys when mutation tracking is enabled
+    // or the tree has subscribers
+    pub(super) changes: Option<ChangeSet<T, L>>,
+
+    // track_mutations is set when the caller asked for the changes on commit
+    pub(super) track_mutations: bool,
+
+    // signals of the replaced nodes and leaves, fired on commit
+    pub(super) signals: Vec<PendingSignal<T, L>>,
+
+    // lineage of the tree, whose subscribers are notified on commit
+    pub(super) lineage: Arc<Lineage<T, L>>,
+
+    // arena the nodes created by the transaction are taken from, if any
+    pub(super) arena: Option<NodeArena<T, L>>,
+
+    // fills the edges of the nodes detached while the nodes along a path are modified
+    pub(super) placeholder: Arc<Node<T, L>>,
 }
 
-impl<T: NodeValue> Clone for Txn<T> {
+impl<T: NodeValue, L: EdgeLabel> Clone for Txn<T, L> {
     fn clone(&self) -> Self {
+        // both transactions share the nodes created so far,
+        // so neither of them modifies those nodes in place anymore
         Txn {
-            root: RwLock::new(self.root.read().clone()),
+            root: self.root.clone(),
             size: AtomicU32::new(self.size.load(atomic::Ordering::Relaxed)),
-            writable: None,
+            id: next_txn_id(),
+            base_seq: self.base_seq,
+            cloned_nodes: self.cloned_nodes,
+            collation: self.collation.clone(),
+            monoid: self.monoid.clone(),
+            changes: self.changes.clone(),
+            track_mutations: self.track_mutations,
+            signals: self.signals.clone(),
+            lineage: self.lineage.clone(),
+            arena: self.arena.clone(),
+            placeholder: self.placeholder.clone(),
         }
     }
 }
 
+/// Writer modifies the nodes of a transaction. It borrows the state of the transaction apart
+/// from the root, so the nodes under the root can be modified in place while it is descended.
+pub(super) struct Writer<'a, T, L>
+where
+    T: NodeValue,
+    L: EdgeLabel,
+{
+    collation: &'a dyn Collation<L>,
+    monoid: Option<&'a dyn Monoid<T>>,
+    cloned_nodes: &'a mut usize,
+    changes: &'a mut Option<ChangeSet<T, L>>,
+    signals: &'a mut Vec<PendingSignal<T, L>>,
+    arena: Option<&'a NodeArena<T, L>>,
+    placeholder: &'a Arc<Node<T, L>>,
+}
+
+/// A node visited by `retain`, whose leaves and children are checked before it is copied, so
+/// untouched subtrees are never copied.
+struct RetainVisit<T, L>
+where
+    T: NodeValue,
+    L: EdgeLabel,
+{
+    node: Arc<Node<T, L>>,
+    next_edge: usize,
+    remove_leaf: bool,
+
+    // new nodes of the changed children by edge index in ascending order
+    changed: Vec<(usize, RetainedNode<T, L>)>,
+}
+
+/// The new node of a subtree changed by `retain`, none if it has been emptied.
+type RetainedNode<T, L> = Option<Arc<Node<T, L>>>;
+
 /// Internal helper methods for Txn
-impl<T: NodeValue> Txn<T> {
-    fn internal_insert(
-        &mut self,
-        node: Arc<Node<T>>,
-        key: &str,
-        search: &str,
-        value: T,
-    ) -> (Option<Arc<Node<T>>>, Option<T>) {
-        // reach the end of the search key,
-        // replace the leaf node with the new leaf node(new value)
-        if search.is_empty() {
-            let mut old_value: Option<T> = None;
-            if node.is_leaf() {
-                let leaf_value = node.get_value().unwrap();
-                old_value = Some(leaf_value);
-            }
+impl<T: NodeValue, L: EdgeLabel> Txn<T, L> {
+    /// writer splits the transaction into its root and the writer of the nodes under it.
+    pub(super) fn writer(&mut self) -> (&mut Arc<Node<T, L>>, Writer<'_, T, L>) {
+        let writer = Writer {
+            collation: self.collation.as_ref(),
+            monoid: self.monoid.as_deref(),
+            cloned_nodes: &mut self.cloned_nodes,
+            changes: &mut self.changes,
+            signals: &mut self.signals,
+            arena: self.arena.as_ref(),
+            placeholder: &self.placeholder,
+        };
+        (&mut self.root, writer)
+    }
 
-            let new_node = self.get_writable_node(node);
-            let leaf_node = LeafNode::new(key, value);
-            new_node.replace_leaf(Some(leaf_node));
-            return (Some(new_node), old_value);
-        }
-
-        let node_edge = node.get_edge(search.as_bytes()[0]);
-
-        // no edge found, insert new edge
-        if node_edge.is_none() {
-            let new_leaf_node = LeafNode::new(key, value);
-            let new_node = Node::new(search, new_leaf_node.into());
-            let new_edge = Edge::new(search.as_bytes()[0], new_node.into());
-            let writable_node = self.get_writable_node(node);
-            writable_node.add_edge(new_edge);
-            return (Some(writable_node), None);
-        }
-
-        let (edge_idx, child_node) = node_edge.unwrap();
-
-        let common_prefix_len = longest_prefix(search, child_node.prefix.read().as_str());
-        if common_prefix_len == child_node.prefix.read().len() {
-            let new_search = &search[common_prefix_len..];
-            let (new_child_node, old_value) =
-                self.internal_insert(child_node, key, new_search, value);
-            if let Some(new_child_node) = new_child_node {
-                let writable_node = self.get_writable_node(node);
-                let new_edge = Edge::new(search.as_bytes()[0], new_child_node);
-                // TODO: maybe we should use `replace_edge` here
-                writable_node.replace_edge_at(edge_idx, new_edge);
-                return (Some(writable_node), old_value);
+    /// find_path descends from the root along the given search key as far as it fully matches
+    /// the node prefixes, returning the path taken, the leaf of the key if it exists and the
+    /// length of the search key consumed.
+    pub(super) fn find_path(&self, search: &[L]) -> (Path, Option<Arc<LeafNode<T, L>>>, usize) {
+        let mut path = Vec::new();
+        let mut node = self.root.as_ref();
+        let mut consumed = 0;
+        while consumed < search.len() {
+            let Some((edge_idx, child_node)) =
+                node.find_edge(&search[consumed], self.collation.as_ref())
+            else {
+                break;
+            };
+            if !search[consumed..].starts_with(&child_node.prefix) {
+                break;
             }
-            return (None, old_value);
+            consumed += child_node.prefix.len();
+            path.push(edge_idx);
+            node = child_node;
         }
 
-        // split the node at the current longest common prefix
-        // between the search key and the child node's prefix
-        let split_node: Arc<Node<T>> = Arc::new(Node::new(&search[..common_prefix_len], None));
-
-        let writable_node = self.get_writable_node(node);
-        writable_node.replace_edge(Edge::new(search.as_bytes()[0], split_node.clone()));
+        let leaf = if consumed == search.len() {
+            node.leaf.clone()
+        } else {
+            None
+        };
+        (path, leaf, consumed)
+    }
+}
 
-        // move the existing child node under the split node
-        let modified_child_node = self.get_writable_node(child_node);
-        split_node.add_edge(Edge::new(
-            modified_child_node.prefix.read().as_bytes()[common_prefix_len],
-            modified_child_node.clone(),
-        ));
-        {
-            // update the prefix of the modified child node to remove the split node common prefix
-            let mut prefix_write_guard = modified_child_node.prefix.write();
-            prefix_write_guard.replace_range(..common_prefix_len, "");
+impl<T: NodeValue, L: EdgeLabel> Writer<'_, T, L> {
+    /// writable_node returns the given node for modification. The node is modified in place if
+    /// the transaction owns it, i.e. it is not shared with any tree, savepoint or other
+    /// transaction. Otherwise it is copied first.
+    pub(super) fn writable_node<'n>(
+        &mut self,
+        node: &'n mut Arc<Node<T, L>>,
+    ) -> &'n mut Node<T, L> {
+        if let Some(owned_node) = Arc::get_mut(node) {
+            // a committed node no longer held by any tree is modified in place, so whoever
+            // watches it is signalled as if it was copied
+            if let Some(watch) = owned_node.signal.take() {
+                self.signals.push(PendingSignal::Watch(watch));
+            }
+        } else {
+            // the original node is replaced once the transaction is committed
+            self.signal_node(node, true);
+            *self.cloned_nodes += 1;
+            *node = match self.arena {
+                Some(arena) => arena.copy_node(node),
+                None => Arc::new((**node).clone()),
+            };
         }
+        Arc::get_mut(node).expect("a copied node is owned by the transaction")
+    }
 
-        // update search to remove the split node common prefix
-        let search = &search[common_prefix_len..];
-
-        // create new leaf node and associate with the split node
-        let new_leaf_node = LeafNode::new(key, value);
+    /// signal_node queues the signal of a node replaced or removed by the transaction. A node still
+    /// shared with other trees is kept until the commit, as it may be watched through them.
+    fn signal_node(&mut self, node: &Arc<Node<T, L>>, shared: bool) {
+        if let Some(watch) = node.signal.get() {
+            self.signals.push(PendingSignal::Watch(watch.clone()));
+        } else if shared {
+            self.signals.push(PendingSignal::Node(node.clone()));
+        }
+    }
 
-        // reach the end of the search key,
-        // associate the new leaf node with the split node
-        if search.is_empty() {
-            split_node.replace_leaf(Some(new_leaf_node));
-            return (Some(writable_node), None);
+    /// new_node creates a node owned by the transaction, taken from the arena if any.
+    fn new_node(&mut self, prefix: &[L], leaf: Option<Arc<LeafNode<T, L>>>) -> Arc<Node<T, L>> {
+        match self.arena {
+            Some(arena) => arena.new_node(prefix, leaf),
+            None => Arc::new(match leaf {
+                Some(leaf) => Node::with_leaf(prefix, leaf),
+                None => Node::new(prefix, None),
+            }),
         }
+    }
 
-        let new_edge = Edge::new(
-            search.as_bytes()[0],
-            Node::new(search, new_leaf_node.into()).into(),
+    /// new_leaf_edge creates the edge to a new node holding the given leaf at the given search key.
+    fn new_leaf_edge(&mut self, search: &[L], leaf: Arc<LeafNode<T, L>>) -> Edge<T, L> {
+        let mut new_node = self.new_node(search, Some(leaf));
+        self.update_summary(
+            Arc::get_mut(&mut new_node).expect("a new node is owned by the transaction"),
         );
-        split_node.add_edge(new_edge);
-
-        (Some(writable_node), None)
+        Edge::new(search[0].clone(), new_node)
     }
 
-    /// get_writable_node returns a new modifiable node for the current transaction if the given node has not been modified
-    /// otherwise, it returns the existing modified node in the current transaction
-    fn get_writable_node(&mut self, node: Arc<Node<T>>) -> Arc<Node<T>> {
-        // TODO: maybe we should create new type on top of `Node<T>` to expose the mutable methods
-
-        if self.writable.is_none() {
-            let lru = LruCache::new(DEFAULT_MODIFIED_CACHE_SIZE);
-            self.writable.replace(lru);
+    /// modify_path makes the nodes along the given path writable and applies the given function
+    /// to the node at the end of it, which receives whether that node is the root.
+    /// On the way back up, an emptied node is removed from its parent and the aggregates are updated.
+    pub(super) fn modify_path<R>(
+        &mut self,
+        node: &mut Arc<Node<T, L>>,
+        path: &[usize],
+        is_root: bool,
+        f: impl FnOnce(&mut Self, &mut Arc<Node<T, L>>, bool) -> R,
+    ) -> R {
+        let mut ancestors = Vec::with_capacity(path.len());
+        let mut current = std::mem::replace(node, self.placeholder.clone());
+        for &edge_idx in path {
+            current = self.descend(&mut ancestors, current, edge_idx);
         }
 
-        // current node has been modified in this transaction
-        // return the existing modified node
-        if self.writable.as_ref().unwrap().contains(&node) {
-            return node;
-        }
+        let result = f(self, &mut current, is_root && ancestors.is_empty());
+        *node = self.ascend(ancestors, current, |writer, node, edge_idx, is_top| {
+            writer.compact_edge(node, edge_idx, is_root && is_top);
+        });
+        result
+    }
 
-        // clone the node to prevent modifying the original node
-        let clone_node = Arc::new((*node).clone());
-        self.writable.as_mut().unwrap().put(clone_node.clone(), ());
-        clone_node
+    /// descend makes the given node writable and detaches the node of the given edge from it,
+    /// pushing the node on the given ancestors. Detached nodes are owned by the descent, so they
+    /// can be updated bottom-up once their children are done without recursing.
+    fn descend(
+        &mut self,
+        ancestors: &mut Vec<(Arc<Node<T, L>>, usize)>,
+        mut node: Arc<Node<T, L>>,
+        edge_idx: usize,
+    ) -> Arc<Node<T, L>> {
+        let writable_node = self.writable_node(&mut node);
+        let child_node = std::mem::replace(
+            writable_node.edge_node_mut(edge_idx),
+            self.placeholder.clone(),
+        );
+        ancestors.push((node, edge_idx));
+        child_node
     }
 
-    fn internal_delete(
+    /// ascend reattaches the given node to the ancestors detached by descend, applying the given
+    /// function to each ancestor bottom-up once its child is back, which receives whether that
+    /// ancestor is the topmost one. Returns the topmost node.
+    fn ascend(
         &mut self,
-        node: Arc<Node<T>>,
-        search: &str,
-    ) -> (Option<Arc<Node<T>>>, Option<Arc<LeafNode<T>>>) {
-        if search.is_empty() {
-            if !node.is_leaf() {
-                return (None, None);
-            }
+        mut ancestors: Vec<(Arc<Node<T, L>>, usize)>,
+        mut node: Arc<Node<T, L>>,
+        mut f: impl FnMut(&mut Self, &mut Node<T, L>, usize, bool),
+    ) -> Arc<Node<T, L>> {
+        while let Some((mut parent, edge_idx)) = ancestors.pop() {
+            let writable_parent =
+                Arc::get_mut(&mut parent).expect("a detached node is owned by the transaction");
+            *writable_parent.edge_node_mut(edge_idx) = node;
+            f(self, writable_parent, edge_idx, ancestors.is_empty());
+            node = parent;
+        }
+        node
+    }
 
-            let new_node = self.get_writable_node(node.clone());
-            let node_leaf = new_node.leaf.write().take();
+    /// compact_edge removes the edge at the given index if its node has been emptied, merging the
+    /// given node with its remaining child if possible, and updates the aggregate of the node.
+    fn compact_edge(&mut self, node: &mut Node<T, L>, edge_idx: usize, is_root: bool) {
+        let child_node = node.edges()[edge_idx].get_node();
+        if !child_node.is_leaf() && child_node.empty_edge() {
+            node.delete_edge_at(edge_idx);
 
-            let should_merge_child =
-                self.root.read().as_ref() != node.as_ref() && new_node.edge_len() == 1;
+            let should_merge_child = !is_root && !node.is_leaf() && node.edge_len() == 1;
             if should_merge_child {
-                self.merge_child(new_node.as_ref());
+                self.merge_child(node);
             }
-            return (Some(new_node), node_leaf);
         }
+        self.update_summary(node);
+    }
 
-        let label = search.as_bytes()[0];
-        let node_edge = node.get_edge(label);
-        if node_edge.is_none()
-            || node_edge.as_ref().is_some_and(|(_, child_node)| {
-                !search.starts_with(child_node.prefix.read().as_str())
-            })
-        {
-            return (None, None);
-        }
+    /// internal_insert inserts the given leaf under the given node at the given search key,
+    /// returning the leaf it replaced if any.
+    pub(super) fn internal_insert(
+        &mut self,
+        node: &mut Arc<Node<T, L>>,
+        search: &[L],
+        leaf: Arc<LeafNode<T, L>>,
+    ) -> Option<Arc<LeafNode<T, L>>> {
+        // descend as long as the search key covers the child node's prefix
+        let mut ancestors = Vec::new();
+        let mut current = std::mem::replace(node, self.placeholder.clone());
+        let mut search = search;
+        let old_leaf = loop {
+            // reach the end of the search key,
+            // replace the leaf node with the new leaf node(new value)
+            if search.is_empty() {
+                let writable_node = self.writable_node(&mut current);
+                let old_leaf = writable_node.leaf.replace(leaf);
+                self.update_summary(writable_node);
+                break old_leaf;
+            }
 
-        let (edge_idx, child_node) = node_edge.unwrap();
+            let Some((edge_idx, child_node)) = current.find_edge(&search[0], self.collation) else {
+                // no edge found, insert new edge
+                let new_edge = self.new_leaf_edge(search, leaf);
+                let writable_node = self.writable_node(&mut current);
+                writable_node.add_edge(new_edge, self.collation);
+                self.update_summary(writable_node);
+                break None;
+            };
+
+            let common_prefix_len = longest_prefix(search, &child_node.prefix);
+            if common_prefix_len == child_node.prefix.len() {
+                search = &search[common_prefix_len..];
+                current = self.descend(&mut ancestors, current, edge_idx);
+                continue;
+            }
 
-        // continue searching in the child node
-        let search = &search[child_node.prefix.read().len()..];
-        let (new_child_node, leaf) = self.internal_delete(child_node, search);
-        if new_child_node.is_none() {
-            return (None, None);
-        }
+            self.split_edge(&mut current, edge_idx, search, common_prefix_len, leaf);
+            break None;
+        };
 
-        let new_child_node = new_child_node.unwrap();
-        let writable_node = self.get_writable_node(node.clone());
-        if !new_child_node.is_leaf() && new_child_node.edge_len() == 0 {
-            writable_node.delete_edge(label);
+        *node = self.ascend(ancestors, current, |writer, node, _, _| {
+            writer.update_summary(node);
+        });
+        old_leaf
+    }
 
-            let should_merge_child = self.root.read().as_ref() != node.as_ref()
-                && !writable_node.is_leaf()
-                && writable_node.edge_len() == 1;
-            if should_merge_child {
-                self.merge_child(&writable_node);
-            }
+    /// split_edge splits the node of the given edge at the given length of the search key, which
+    /// the node's prefix shares with it, and inserts the given leaf under the split node.
+    fn split_edge(
+        &mut self,
+        node: &mut Arc<Node<T, L>>,
+        edge_idx: usize,
+        search: &[L],
+        common_prefix_len: usize,
+        leaf: Arc<LeafNode<T, L>>,
+    ) {
+        let writable_node = self.writable_node(node);
+
+        // split the node at the current longest common prefix
+        // between the search key and the child node's prefix
+        let split_node = self.new_node(&search[..common_prefix_len], None);
+        let mut modified_child_node =
+            std::mem::replace(writable_node.edge_node_mut(edge_idx), split_node);
+        let split_node = Arc::get_mut(writable_node.edge_node_mut(edge_idx))
+            .expect("a new node is owned by the transaction");
+
+        // move the existing child node under the split node,
+        // removing the split node common prefix from its prefix
+        let label = {
+            let modified_child_node = self.writable_node(&mut modified_child_node);
+            modified_child_node.prefix.remove_front(common_prefix_len);
+            modified_child_node.prefix[0].clone()
+        };
+        split_node.add_edge(Edge::new(label, modified_child_node), self.collation);
+
+        // update search to remove the split node common prefix
+        let search = &search[common_prefix_len..];
+
+        // reach the end of the search key,
+        // associate the new leaf node with the split node
+        if search.is_empty() {
+            split_node.leaf.replace(leaf);
         } else {
-            writable_node.replace_edge_at(edge_idx, Edge::new(label, new_child_node));
+            let new_edge = self.new_leaf_edge(search, leaf);
+            split_node.add_edge(new_edge, self.collation);
         }
 
-        (Some(writable_node), leaf)
+        self.update_summary(split_node);
+        self.update_summary(writable_node);
     }
 
-    fn internal_delete_prefix(
+    /// remove_leaf removes the leaf of the given node, returning it. Unless the node is the root,
+    /// it is merged with its only child, and an emptied node is left for its parent to remove.
+    pub(super) fn remove_leaf(
         &mut self,
-        node: Arc<Node<T>>,
-        search: &str,
-    ) -> (Option<Arc<Node<T>>>, u32) {
-        if search.is_empty() {
-            let writable_node = self.get_writable_node(node.clone());
-            if node.is_leaf() {
-                writable_node.leaf.write().take();
+        node: &mut Arc<Node<T, L>>,
+        is_root: bool,
+    ) -> Option<Arc<LeafNode<T, L>>> {
+        let writable_node = self.writable_node(node);
+        let old_leaf = writable_node.leaf.take();
+
+        let should_merge_child = !is_root && writable_node.edge_len() == 1;
+        if should_merge_child {
+            self.merge_child(writable_node);
+        }
+        self.update_summary(writable_node);
+        old_leaf
+    }
+
+    /// clear_subtree removes every leaf of the subtree rooted at the given node,
+    /// returning the number of removed leaves. The emptied node is left for its parent to remove.
+    fn clear_subtree(&mut self, node: &mut Arc<Node<T, L>>) -> u32 {
+        let mut deleted_count = 0;
+        // nodes to remove, with whether they are still reachable from other trees
+        let mut stack = vec![(&*node, false)];
+        while let Some((current_node, parent_shared)) = stack.pop() {
+            // every removed node may be watched for a key or prefix under it
+            let shared = parent_shared || Arc::strong_count(current_node) > 1;
+            self.signal_node(current_node, shared);
+            if let Some(leaf) = current_node.leaf.as_ref() {
+                self.record_change(Some(leaf.clone()), None);
+                deleted_count += 1;
             }
-            writable_node.reset_edges();
-            return (Some(writable_node), self.count_node(node.as_ref()));
+            stack.extend(
+                current_node
+                    .edges()
+                    .iter()
+                    .map(|edge| (edge.get_shared_node(), shared)),
+            );
         }
 
-        let mut search = search;
-        let label = search.as_bytes()[0];
-        let node_edge = node.get_edge(label);
-        if node_edge.is_none()
-            || node_edge.as_ref().is_some_and(|(_, child_node)| {
-                let child_prefix = child_node.prefix.read();
-                let child_prefix_str = child_prefix.as_str();
-                !child_prefix_str.starts_with(search) && !search.starts_with(child_prefix_str)
-            })
-        {
-            return (None, 0);
+        let cleared_node = std::mem::replace(node, self.new_node(&[], None));
+        if let Some(arena) = self.arena {
+            arena.release([cleared_node]);
         }
+        deleted_count
+    }
 
-        let (edge_idx, child_node) = node_edge.unwrap();
+    /// update_subtree rewrites the value of every leaf in the subtree rooted at the given node,
+    /// returning the number of updated leaves. The subtree is walked with the detached ancestors
+    /// instead of recursing, updating the aggregates bottom-up.
+    fn update_subtree(
+        &mut self,
+        node: &mut Arc<Node<T, L>>,
+        f: &mut impl FnMut(&[L], &T) -> T,
+    ) -> u32 {
+        let mut ancestors = Vec::new();
+        let mut current = std::mem::replace(node, self.placeholder.clone());
+        let mut updated_count = self.update_leaf(&mut current, f);
+        let mut next_edge = 0;
+        loop {
+            if next_edge < current.edge_len() {
+                current = self.descend(&mut ancestors, current, next_edge);
+                updated_count += self.update_leaf(&mut current, f);
+                next_edge = 0;
+                continue;
+            }
 
-        if child_node.prefix.read().as_str().len() > search.len() {
-            search = "";
-        } else {
-            search = &search[child_node.prefix.read().as_str().len()..];
+            let writable_node =
+                Arc::get_mut(&mut current).expect("an updated node is owned by the transaction");
+            self.update_summary(writable_node);
+            let Some((mut parent, edge_idx)) = ancestors.pop() else {
+                break;
+            };
+            let writable_parent =
+                Arc::get_mut(&mut parent).expect("a detached node is owned by the transaction");
+            *writable_parent.edge_node_mut(edge_idx) = current;
+            current = parent;
+            next_edge = edge_idx + 1;
         }
+        *node = current;
+        updated_count
+    }
 
-        let (new_child_node, deleted_count) = self.internal_delete_prefix(child_node, search);
-        if new_child_node.is_none() {
-            return (None, 0);
-        }
+    /// update_leaf rewrites the value of the leaf of the given node if any, returning the number
+    /// of updated leaves.
+    fn update_leaf(
+        &mut self,
+        node: &mut Arc<Node<T, L>>,
+        f: &mut impl FnMut(&[L], &T) -> T,
+    ) -> u32 {
+        let writable_node = self.writable_node(node);
+        let Some(leaf) = writable_node.leaf.as_ref() else {
+            return 0;
+        };
+        let value = f(leaf.get_key(), leaf.get_value());
+        let new_leaf = Arc::new(LeafNode::new(leaf.get_key(), value));
+        let old_leaf = writable_node.leaf.replace(new_leaf.clone());
+        self.record_change(old_leaf, Some(new_leaf));
+        1
+    }
+
+    /// retain_subtree removes the leaves of the subtree rooted at the given node which do not
+    /// match the given predicate in a single post-order traversal, returning the number of
+    /// removed leaves. A node is only copied once its leaf or one of its children changes, and
+    /// the emptied nodes are compacted along the way.
+    fn retain_subtree(
+        &mut self,
+        node: &mut Arc<Node<T, L>>,
+        pred: &mut impl FnMut(&[L], &T) -> bool,
+    ) -> u32 {
+        let mut removed_count = 0;
+        let mut visit = |writer: &mut Self, node: Arc<Node<T, L>>| {
+            let remove_leaf = node
+                .leaf
+                .as_ref()
+                .is_some_and(|leaf| !pred(leaf.get_key(), leaf.get_value()));
+            if remove_leaf {
+                writer.record_change(node.leaf.clone(), None);
+                removed_count += 1;
+            }
+            RetainVisit {
+                node,
+                next_edge: 0,
+                remove_leaf,
+                changed: Vec::new(),
+            }
+        };
+
+        let mut stack = vec![visit(self, node.clone())];
+        loop {
+            let current = stack
+                .last_mut()
+                .expect("the stack holds the root until the end");
+            if let Some(child_node) = current.node.get_edge_at(current.next_edge) {
+                current.next_edge += 1;
+                let child_visit = visit(self, child_node);
+                stack.push(child_visit);
+                continue;
+            }
 
-        let new_child_node = new_child_node.unwrap();
-        let writable_node = self.get_writable_node(node.clone());
+            let current = stack.pop().expect("the stack holds the current node");
+            let is_root = stack.is_empty();
+            if !current.remove_leaf && current.changed.is_empty() {
+                if is_root {
+                    break;
+                }
+                continue;
+            }
 
-        if !new_child_node.is_leaf() && new_child_node.edge_len() == 0 {
-            writable_node.delete_edge(label);
-            let should_merge_child = self.root.read().as_ref() != node.as_ref()
-                && !writable_node.is_leaf()
-                && writable_node.edge_len() == 1;
-            if should_merge_child {
-                self.merge_child(&writable_node);
+            // a node left without leaf nor children is removed by its parent without a copy
+            let emptied_edges = current.changed.iter().filter(|(_, child)| child.is_none());
+            if !is_root
+                && (current.remove_leaf || !current.node.is_leaf())
+                && emptied_edges.count() == current.node.edge_len()
+            {
+                self.signal_node(&current.node, true);
+                let parent = stack
+                    .last_mut()
+                    .expect("a node other than the root has a parent");
+                parent.changed.push((parent.next_edge - 1, None));
+                continue;
             }
-        } else {
-            writable_node.replace_edge_at(edge_idx, Edge::new(label, new_child_node));
+
+            let mut new_node = current.node;
+            let writable_node = self.writable_node(&mut new_node);
+            if current.remove_leaf {
+                writable_node.leaf = None;
+            }
+            // apply from the last edge so the deleted edges do not shift the indexes
+            for (edge_idx, child_node) in current.changed.into_iter().rev() {
+                match child_node {
+                    Some(child_node) => *writable_node.edge_node_mut(edge_idx) = child_node,
+                    None => writable_node.delete_edge_at(edge_idx),
+                }
+            }
+
+            if !is_root && !writable_node.is_leaf() && writable_node.edge_len() == 1 {
+                self.merge_child(writable_node);
+            }
+            self.update_summary(writable_node);
+
+            let Some(parent) = stack.last_mut() else {
+                *node = new_node;
+                break;
+            };
+            parent.changed.push((parent.next_edge - 1, Some(new_node)));
         }
-        (Some(writable_node), deleted_count)
+        removed_count
     }
 
     /// merge_child is used to collapse the given node with its child.
     /// This should only be called when the given node is not a leaf and has a single edge.
-    fn merge_child(&mut self, node: &Node<T>) {
+    pub(super) fn merge_child(&mut self, node: &mut Node<T, L>) {
         assert!(!node.is_leaf(), "cannot merge a leaf node");
         assert!(
             node.edge_len() == 1,
@@ -284,118 +637,335 @@ impl<T: NodeValue> Txn<T> {
         );
 
         let child_edge = node.pop_edge().expect("node should have at least one edge");
+        let shared_child = child_edge.get_shared_node();
+        self.signal_node(shared_child, Arc::strong_count(shared_child) > 1);
         let child_node = child_edge.get_node();
 
-        {
-            // merge the prefixes
-            let mut write_guard = node.prefix.write();
-            write_guard.push_str(child_node.prefix.read().as_str());
+        // merge the prefixes
+        node.prefix.extend_from_slice(&child_node.prefix);
 
-            // move the leaf node from the child to the parent
-            let mut child_leaf_write_guard = child_node.leaf.write();
-            let child_leaf = child_leaf_write_guard.take();
+        // move the leaf node from the child to the parent,
+        // the child itself may be shared with other versions so it is left untouched
+        node.leaf = child_node.leaf.clone();
 
-            let mut leaf_write = node.leaf.write();
-            *leaf_write = child_leaf;
-        }
+        // the merged node covers the same subtree as the child
+        node.summary = child_node.summary.clone();
 
         if child_node.edge_len() > 0 {
-            child_node.collect_into_edges(&node.edges);
+            child_node.collect_into_edges(&mut node.edges);
         } else {
             node.reset_edges();
         }
+
+        if let Some(arena) = self.arena {
+            arena.release([child_edge.into_node()]);
+        }
     }
 
-    /// count_node returns the number of leaf nodes in the subtree rooted at the given node.
-    fn count_node(&self, node: &Node<T>) -> u32 {
-        let mut count = 0;
-        if node.is_leaf() {
-            count += 1;
+    /// update_summary recomputes the aggregate of the given modified node if the tree has one.
+    pub(super) fn update_summary(&self, node: &mut Node<T, L>) {
+        if let Some(monoid) = self.monoid {
+            node.update_summary(monoid);
+        }
+    }
+
+    /// record_change records the change of a key, tracking the signal of the old leaf,
+    /// and adds it to the change set if mutation tracking is enabled.
+    pub(super) fn record_change(
+        &mut self,
+        old_leaf: Option<Arc<LeafNode<T, L>>>,
+        new_leaf: Option<Arc<LeafNode<T, L>>>,
+    ) {
+        if let Some(old_leaf) = old_leaf.as_ref() {
+            if let Some(watch) = old_leaf.signal().get() {
+                self.signals.push(PendingSignal::Watch(watch.clone()));
+            } else if Arc::strong_count(old_leaf) > 1 {
+                // the leaf may still be watched through the trees sharing it
+                self.signals.push(PendingSignal::Leaf(old_leaf.clone()));
+            }
+        }
+        if let Some(changes) = self.changes.as_mut() {
+            changes.record(self.collation, old_leaf, new_leaf);
         }
-        // recursively count child nodes
-        node.for_each_edge(|edge| count += self.count_node(edge.get_node()));
-        count
     }
 }
 
 /// Public APIs for Txn
-impl<T: NodeValue> Txn<T> {
+impl<T: NodeValue, L: EdgeLabel> Txn<T, L> {
     /// Get the number of nodes in the transaction.
     pub fn len(&self) -> u32 {
         self.size.load(Ordering::Relaxed)
     }
 
+    /// Get the number of nodes copied by the transaction so far.
+    pub fn cloned_nodes(&self) -> usize {
+        self.cloned_nodes
+    }
+
     /// Get the root node of the transaction.
-    pub fn root(&self) -> Arc<Node<T>> {
-        self.root.read().clone()
+    pub fn root(&self) -> Arc<Node<T, L>> {
+        self.root.clone()
     }
 
     // Retrieve the value associated with the given key if exists.
-    pub fn get(&self, key: &str) -> Option<T> {
-        let root = self.root.read();
-        root.get(key)
+    pub fn get(&self, key: impl AsRef<[L]>) -> Option<T> {
+        let search = self.collation.normalize(key.as_ref());
+        self.root.get(&search, self.collation.as_ref())
+    }
+
+    /// Retrieve the leaf holding the given key if exists, without cloning the value.
+    pub fn get_ref(&self, key: impl AsRef<[L]>) -> Option<Arc<LeafNode<T, L>>> {
+        let search = self.collation.normalize(key.as_ref());
+        self.root.get_leaf(&search, self.collation.as_ref())
+    }
+
+    /// Call the given function with a reference to the value of the given key if exists.
+    pub fn with_value<R>(&self, key: impl AsRef<[L]>, f: impl FnOnce(&T) -> R) -> Option<R> {
+        self.get_ref(key).map(|leaf| f(leaf.get_value()))
+    }
+
+    /// Get the aggregate of all the values whose key starts with the given prefix.
+    /// Returns `None` if no key has the prefix or the tree has no aggregate.
+    pub fn aggregate_prefix(&self, prefix: impl AsRef<[L]>) -> Option<T> {
+        let search = self.collation.normalize(prefix.as_ref());
+        self.root.aggregate_prefix(&search, self.collation.as_ref())
     }
 
     /// Add/Update a given key. If the key already exists, its value is updated and the old value is returned.
-    pub fn insert(&mut self, key: &str, value: T) -> Option<T> {
-        let root = self.root.read().clone();
-        let (new_node, old_value) = self.internal_insert(root, key, key, value);
+    pub fn insert(&mut self, key: impl AsRef<[L]>, value: T) -> Option<T> {
+        let key = key.as_ref();
+        let collation = self.collation.clone();
+        let search = collation.normalize(key);
+        let leaf = Arc::new(LeafNode::new(key, value));
 
-        if let Some(node) = new_node {
-            let mut root_guard = self.root.write();
-            *root_guard = node;
-        }
+        let (root, mut writer) = self.writer();
+        let old_leaf = writer.internal_insert(root, &search, leaf.clone());
+        writer.record_change(old_leaf.clone(), Some(leaf));
 
-        if old_value.is_none() {
+        if old_leaf.is_none() {
             // TODO: revisit the memory ordering here
             self.size.fetch_add(1, atomic::Ordering::Relaxed);
         }
-        old_value
+        old_leaf.map(|leaf| leaf.get_value().clone())
     }
 
     /// Removes the given key from the tree. If the key exists, its value is returned.
-    pub fn delete(&mut self, key: &str) -> Option<T> {
-        let root = self.root.read().clone();
-        let (new_root, old_value) = self.internal_delete(root, key);
-        if let Some(new_root) = new_root {
-            let mut root_guard = self.root.write();
-            *root_guard = new_root;
+    pub fn delete(&mut self, key: impl AsRef<[L]>) -> Option<T> {
+        match self.entry(key) {
+            Entry::Occupied(entry) => Some(entry.remove()),
+            Entry::Vacant(_) => None,
         }
-        if let Some(old_value) = old_value {
-            self.size.fetch_sub(1, Ordering::Relaxed);
-            // TODO: revisit to unwrap from Arc
-            return Some(old_value.get_value().clone());
-        }
-        None
     }
 
     /// Removes all keys with the given prefix from the tree.
     /// Returns true if any keys were deleted.
-    pub fn delete_prefix(&mut self, prefix: &str) -> bool {
-        let root = self.root.read().clone();
-        let (new_root, deleted_count) = self.internal_delete_prefix(root, prefix);
-        if let Some(new_root) = new_root {
-            let mut root_guard = self.root.write();
-            *root_guard = new_root;
-            self.size.fetch_sub(deleted_count, Ordering::Relaxed);
-            return true;
+    pub fn delete_prefix(&mut self, prefix: impl AsRef<[L]>) -> bool {
+        let collation = self.collation.clone();
+        let search = collation.normalize(prefix.as_ref());
+        let Some((path, found)) = self.root.find_prefix(&search, collation.as_ref()) else {
+            return false;
+        };
+        // an empty root has nothing to change, so the path is neither copied nor signalled
+        if !found.is_leaf() && found.empty_edge() {
+            return false;
         }
-        false
+
+        let (root, mut writer) = self.writer();
+        let deleted_count = writer.modify_path(root, &path, true, |writer, node, _| {
+            writer.clear_subtree(node)
+        });
+        self.size.fetch_sub(deleted_count, Ordering::Relaxed);
+        deleted_count > 0
     }
 
-    /// Finalizes the transaction and returns the new tree.
-    pub fn commit(self) -> Tree<T> {
-        // TODO: support notifying subscribers about the changes
-        Tree {
-            root: self.root.read().clone(),
-            size: self.size.load(atomic::Ordering::Relaxed),
+    /// Rewrites the value of every key with the given prefix with the value returned by the
+    /// given function. Only the path to the prefix and the subtree under it are copied.
+    /// Returns true if any keys were updated.
+    pub fn update_prefix(
+        &mut self,
+        prefix: impl AsRef<[L]>,
+        mut f: impl FnMut(&[L], &T) -> T,
+    ) -> bool {
+        let collation = self.collation.clone();
+        let search = collation.normalize(prefix.as_ref());
+        let Some((path, found)) = self.root.find_prefix(&search, collation.as_ref()) else {
+            return false;
+        };
+        // an empty root has nothing to change, so the path is neither copied nor signalled
+        if !found.is_leaf() && found.empty_edge() {
+            return false;
+        }
+
+        let (root, mut writer) = self.writer();
+        let updated_count = writer.modify_path(root, &path, true, |writer, node, _| {
+            writer.update_subtree(node, &mut f)
+        });
+        updated_count > 0
+    }
+
+    /// Removes all the keys whose key and value do not match the given predicate.
+    /// The tree is traversed once, only the nodes whose leaves or children change are copied and
+    /// the emptied nodes are compacted along the way.
+    pub fn retain(&mut self, mut pred: impl FnMut(&[L], &T) -> bool) {
+        let (root, mut writer) = self.writer();
+        let removed_count = writer.retain_subtree(root, &mut pred);
+        self.size.fetch_sub(removed_count, Ordering::Relaxed);
+    }
+
+    /// Get the entry of the given key for in-place manipulation.
+    /// The key is looked up once, and the entry modifies the nodes along that path.
+    pub fn entry(&mut self, key: impl AsRef<[L]>) -> Entry<'_, T, L> {
+        let key = key.as_ref().to_vec();
+        let search = self.collation.normalize(&key).into_owned();
+        let (path, leaf, consumed) = self.find_path(&search);
+
+        match leaf {
+            Some(leaf) => Entry::Occupied(OccupiedEntry::new(self, path, leaf)),
+            None => Entry::Vacant(VacantEntry::new(self, key, search, path, consumed)),
         }
     }
+
+    /// Insert the given key only if it does not exist yet.
+    /// Returns true if the key was inserted.
+    pub fn insert_if_absent(&mut self, key: impl AsRef<[L]>, value: T) -> bool {
+        match self.entry(key) {
+            Entry::Occupied(_) => false,
+            Entry::Vacant(entry) => {
+                entry.insert(value);
+                true
+            }
+        }
+    }
+
+    /// Replace the value of the given key with `new` only if its current value equals `expected`.
+    /// Returns true if the value was swapped.
+    pub fn compare_and_swap(&mut self, key: impl AsRef<[L]>, expected: &T, new: T) -> bool
+    where
+        T: PartialEq,
+    {
+        match self.entry(key) {
+            Entry::Occupied(mut entry) if entry.get() == expected => {
+                entry.insert(new);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Remove the given key only if its value matches the given predicate.
+    /// Returns the removed value if any.
+    pub fn delete_if(&mut self, key: impl AsRef<[L]>, pred: impl FnOnce(&T) -> bool) -> Option<T> {
+        match self.entry(key) {
+            Entry::Occupied(entry) if pred(entry.get()) => Some(entry.remove()),
+            _ => None,
+        }
+    }
+
+    /// Update the given key with the value returned by the given function, which receives the
+    /// current value if any. Returning `None` removes the key. Returns the old value if any.
+    pub fn update(
+        &mut self,
+        key: impl AsRef<[L]>,
+        f: impl FnOnce(Option<&T>) -> Option<T>,
+    ) -> Option<T> {
+        match self.entry(key) {
+            Entry::Occupied(mut entry) => match f(Some(entry.get())) {
+                Some(value) => Some(entry.insert(value)),
+                None => Some(entry.remove()),
+            },
+            Entry::Vacant(entry) => {
+                if let Some(value) = f(None) {
+                    entry.insert(value);
+                }
+                None
+            }
+        }
+    }
+
+    /// Captures the current state of the transaction, which can be restored with `rollback_to`.
+    ///
+    /// The nodes modified so far are shared with the savepoint, so later changes copy them
+    /// instead of modifying them in place.
+    pub fn savepoint(&mut self) -> Savepoint<T, L> {
+        Savepoint {
+            txn_id: self.id,
+            root: self.root.clone(),
+            size: self.size.load(Ordering::Relaxed),
+            changes: self.changes.clone(),
+            signals_len: self.signals.len(),
+        }
+    }
+
+    /// Discards every change made after the given savepoint was taken.
+    /// The savepoint stays valid and can be rolled back to again.
+    ///
+    /// Panics if the savepoint was taken from another transaction, including a clone of this one.
+    pub fn rollback_to(&mut self, savepoint: &Savepoint<T, L>) {
+        assert_eq!(
+            savepoint.txn_id, self.id,
+            "savepoint taken from another transaction"
+        );
+        // the nodes created after the savepoint are discarded with the root
+        self.root = savepoint.root.clone();
+        self.size.store(savepoint.size, Ordering::Relaxed);
+        if self.changes.is_some() {
+            self.changes = savepoint.changes.clone();
+        }
+        self.signals.truncate(savepoint.signals_len);
+    }
+
+    /// Enables or disables the tracking of the changed keys, which are returned by
+    /// `commit_with_changes`. Disabling the tracking discards the changes recorded so far,
+    /// unless the changes are published to the subscribers of the tree.
+    pub fn track_mutations(&mut self, enabled: bool) {
+        self.track_mutations = enabled;
+        if !enabled && !self.lineage.has_subscribers() {
+            self.changes = None;
+        } else if self.changes.is_none() {
+            self.changes = Some(ChangeSet::default());
+        }
+    }
+
+    /// Finalizes the transaction and returns the new tree with the changed keys,
+    /// which are only available if mutation tracking is enabled.
+    ///
+    /// The watches of the replaced nodes and keys are fired, and the changes are published to
+    /// the subscribers of the tree.
+    pub fn commit_with_changes(self) -> (Tree<T, L>, Option<ChangeSet<T, L>>) {
+        for signal in self.signals.iter() {
+            signal.fire();
+        }
+        let seq = self.lineage.commit(self.base_seq, self.changes.as_ref());
+
+        let tree = Tree {
+            root: self.root,
+            size: self.size.load(atomic::Ordering::Relaxed),
+            seq,
+            collation: self.collation,
+            monoid: self.monoid,
+            lineage: self.lineage,
+            arena: self.arena,
+        };
+        let changes = if self.track_mutations {
+            self.changes
+        } else {
+            None
+        };
+        (tree, changes)
+    }
+
+    /// Finalizes the transaction and returns the new tree.
+    /// The watches of the replaced nodes and keys are fired, and the changes are published to
+    /// the subscribers of the tree.
+    pub fn commit(self) -> Tree<T, L> {
+        self.commit_with_changes().0
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{collation::Binary, tree::ChangeKind};
 
     #[test]
     fn test_txn_clone() {
@@ -412,6 +982,207 @@ mod tests {
         assert_eq!(txn_clone.len(), 2);
     }
 
+    #[test]
+    fn test_txn_clone_isolation() {
+        let tree = Tree::<u32>::new();
+        let mut txn = tree.start_transaction();
+        txn.insert("001", 1);
+        txn.insert("002", 2);
+
+        // the nodes created before the clone are shared and must be copied by both
+        let mut txn_clone = txn.clone();
+        txn.insert("003", 3);
+        txn.insert("001", 10);
+        txn_clone.delete("002");
+
+        assert_eq!(txn.get("001"), Some(10));
+        assert_eq!(txn.get("002"), Some(2));
+        assert_eq!(txn.get("003"), Some(3));
+        assert_eq!(txn_clone.get("001"), Some(1));
+        assert_eq!(txn_clone.get("002"), None);
+        assert_eq!(txn_clone.get("003"), None);
+        assert_eq!(txn.commit().len(), 3);
+        assert_eq!(txn_clone.commit().len(), 1);
+    }
+
+    #[test]
+    fn test_txn_root_snapshot() {
+        let mut txn = Tree::<u32>::new().start_transaction();
+        txn.insert("001", 1);
+        txn.insert("002", 2);
+        assert_eq!(txn.cloned_nodes(), 0);
+
+        // a root handed out is shared, so the nodes under it are copied instead of modified
+        let root = txn.root();
+        txn.insert("001", 10);
+        txn.delete("002");
+        assert!(txn.cloned_nodes() > 0);
+        assert_eq!(root.get(b"001", &Binary), Some(1));
+        assert_eq!(root.get(b"002", &Binary), Some(2));
+        assert_eq!(txn.get("001"), Some(10));
+
+        // once released, the copies are owned by the transaction again
+        drop(root);
+        let cloned_nodes = txn.cloned_nodes();
+        txn.insert("003", 3);
+        txn.insert("001", 11);
+        assert_eq!(txn.cloned_nodes(), cloned_nodes);
+    }
+
+    #[test]
+    fn test_txn_savepoint() {
+        let tree = Tree::<u32>::new();
+        let mut txn = tree.start_transaction();
+        txn.insert("foo", 1);
+        txn.insert("foobar", 2);
+
+        let savepoint = txn.savepoint();
+        let snapshot = txn.root();
+
+        // changes after the savepoint should copy the nodes instead of modifying them in place
+        txn.insert("foo", 10);
+        txn.insert("foobaz", 3);
+        txn.delete("foobar");
+        assert_eq!(txn.len(), 2);
+        assert_eq!(snapshot.get(b"foo", &Binary), Some(1));
+        assert_eq!(snapshot.get(b"foobar", &Binary), Some(2));
+        assert_eq!(snapshot.get(b"foobaz", &Binary), None);
+
+        let nested = txn.savepoint();
+        txn.delete_prefix("foo");
+        assert_eq!(txn.len(), 0);
+        txn.rollback_to(&nested);
+        assert_eq!(txn.get("foo"), Some(10));
+        assert_eq!(txn.get("foobaz"), Some(3));
+
+        txn.rollback_to(&savepoint);
+        assert_eq!(txn.len(), 2);
+        assert_eq!(txn.get("foo"), Some(1));
+        assert_eq!(txn.get("foobar"), Some(2));
+        assert_eq!(txn.get("foobaz"), None);
+
+        // the savepoint stays valid after a rollback
+        txn.insert("foo", 20);
+        txn.rollback_to(&savepoint);
+        assert_eq!(txn.get("foo"), Some(1));
+
+        txn.insert("bar", 4);
+        let tree = txn.commit();
+        assert_eq!(tree.len(), 3);
+        assert_eq!(tree.get("bar"), Some(4));
+        assert_eq!(tree.get("foo"), Some(1));
+    }
+
+    #[test]
+    #[should_panic(expected = "savepoint taken from another transaction")]
+    fn test_txn_rollback_to_foreign_savepoint() {
+        let tree = Tree::<u32>::new();
+        let mut txn = tree.start_transaction();
+        txn.insert("foo", 1);
+        let savepoint = txn.savepoint();
+
+        let mut other_txn = tree.start_transaction();
+        other_txn.rollback_to(&savepoint);
+    }
+
+    #[test]
+    fn test_txn_track_mutations() {
+        let mut txn = Tree::<u32>::new().start_transaction();
+        for (i, key) in ["a", "b", "c/1", "c/2", "c/3", "d"].iter().enumerate() {
+            txn.insert(key, i as u32);
+        }
+        let (tree, changes) = txn.commit_with_changes();
+        assert!(
+            changes.is_none(),
+            "changes should only be tracked when enabled"
+        );
+
+        let mut txn = tree.start_transaction();
+        txn.track_mutations(true);
+        txn.insert("a", 10);
+        txn.insert("a", 11);
+        txn.insert("e", 5);
+        txn.insert("tmp", 6);
+        txn.delete("tmp");
+        txn.delete("b");
+        txn.delete_prefix("c/");
+        txn.entry("d").and_modify(|value| *value += 1);
+        txn.entry("f").or_insert(7);
+        txn.retain(|key, _| key != b"e");
+        txn.update_prefix("f", |_, value| value * 2);
+        let (_, changes) = txn.commit_with_changes();
+        let changes = changes.unwrap();
+
+        let summary: Vec<_> = changes
+            .iter()
+            .map(|change| {
+                (
+                    String::from_utf8(change.key().to_vec()).unwrap(),
+                    change.kind(),
+                    change.old_value().copied(),
+                    change.new_value().copied(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("a".to_string(), ChangeKind::Updated, Some(0), Some(11)),
+                ("b".to_string(), ChangeKind::Deleted, Some(1), None),
+                ("c/1".to_string(), ChangeKind::Deleted, Some(2), None),
+                ("c/2".to_string(), ChangeKind::Deleted, Some(3), None),
+                ("c/3".to_string(), ChangeKind::Deleted, Some(4), None),
+                ("d".to_string(), ChangeKind::Updated, Some(5), Some(6)),
+                ("f".to_string(), ChangeKind::Inserted, None, Some(14)),
+            ]
+        );
+        assert_eq!(changes.iter_kind(ChangeKind::Deleted).count(), 4);
+
+        // rolling back to a savepoint also rolls back the recorded changes
+        let mut txn = tree.start_transaction();
+        txn.track_mutations(true);
+        txn.insert("a", 10);
+        let savepoint = txn.savepoint();
+        txn.delete("b");
+        txn.rollback_to(&savepoint);
+        let (_, changes) = txn.commit_with_changes();
+        let changes = changes.unwrap();
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes.iter().next().unwrap().key(), b"a");
+    }
+
+    #[test]
+    fn test_txn_cloned_nodes() {
+        fn count_nodes(node: &Node<usize>) -> usize {
+            let mut count = 1;
+            node.for_each_edge(|edge| count += count_nodes(edge.get_node()));
+            count
+        }
+        let keys: Vec<_> = (0..300).map(|i| format!("key/{:02}/{i}", i % 17)).collect();
+
+        // nodes created by the transaction are owned by it, only the root is copied
+        let empty = Tree::<usize>::new();
+        let mut txn = empty.start_transaction();
+        for (i, key) in keys.iter().enumerate() {
+            txn.insert(key, i);
+        }
+        assert_eq!(txn.cloned_nodes(), 1);
+        let tree = txn.commit();
+        assert_eq!(tree.len(), 300);
+
+        // every node of the tree is copied exactly once, however many keys of it change
+        let mut txn = tree.start_transaction();
+        for (i, key) in keys.iter().enumerate() {
+            txn.insert(key, i + 1);
+        }
+        assert_eq!(txn.cloned_nodes(), count_nodes(&tree.root()));
+        let updated = txn.commit();
+        for (i, key) in keys.iter().enumerate() {
+            assert_eq!(updated.get(key), Some(i + 1));
+        }
+        assert_eq!(tree.get(&keys[0]), Some(0));
+    }
+
     #[test]
     fn test_txn_len() {
         let tree = Tree::<bool>::new();
@@ -429,12 +1200,12 @@ mod tests {
         let mut txn = tree.start_transaction();
         txn.insert("", true);
 
-        let expected_root = Node::new("", LeafNode::new("", true).into());
+        let expected_root = Node::new(b"", LeafNode::new(b"", true).into());
         assert_eq!(&expected_root, txn.root().as_ref());
 
         txn.insert("key", true);
         let modified_root = txn.root();
-        assert_eq!(modified_root.as_ref(), txn.root.read().as_ref());
+        assert_eq!(modified_root.as_ref(), txn.root.as_ref());
         assert_ne!(&expected_root, modified_root.as_ref());
     }
 
@@ -461,15 +1232,15 @@ mod tests {
             let result = txn.insert("001", 1);
             assert!(result.is_none());
             assert_eq!(txn.size.load(atomic::Ordering::Relaxed), 1);
-            let root = txn.root.read();
-            assert_eq!(root.prefix.read().as_str(), "");
+            let root = &txn.root;
+            assert_eq!(root.prefix.as_slice(), b"");
 
-            let edge = root.get_edge(b'0');
+            let edge = root.get_edge(&b'0', &Binary);
             assert!(edge.is_some());
             let (_, child_node) = edge.unwrap();
             assert_eq!(
                 *child_node,
-                Node::new("001", LeafNode::new("001", 1).into()).into()
+                Node::new(b"001", LeafNode::new(b"001", 1).into())
             );
         }
 
@@ -478,22 +1249,25 @@ mod tests {
             let result = txn.insert("002", 2);
             assert!(result.is_none());
             assert_eq!(txn.size.load(atomic::Ordering::Relaxed), 2);
-            let root = txn.root.read();
-            assert_eq!(root.prefix.read().as_str(), "");
+            let root = &txn.root;
+            assert_eq!(root.prefix.as_slice(), b"");
 
-            let edge = root.get_edge(b'0');
+            let edge = root.get_edge(&b'0', &Binary);
             assert!(edge.is_some());
             let (_, child_node) = edge.unwrap();
             assert_eq!(
                 *child_node,
                 Node::new_with_edges(
-                    "00",
+                    b"00",
                     None,
                     vec![
-                        Edge::new(b'1', Node::new("1", LeafNode::new("001", 1).into()).into()),
+                        Edge::new(
+                            b'1',
+                            Node::new(b"1", LeafNode::new(b"001", 1).into()).into()
+                        ),
                         Edge::new(
                             b'2',
-                            Arc::new(Node::new("2", LeafNode::new("002", 2).into())),
+                            Arc::new(Node::new(b"2", LeafNode::new(b"002", 2).into())),
                         ),
                     ]
                 )
@@ -505,21 +1279,30 @@ mod tests {
             let result = txn.insert("003", 3);
             assert!(result.is_none());
             assert_eq!(txn.size.load(atomic::Ordering::Relaxed), 3);
-            let root = txn.root.read();
-            assert_eq!(root.prefix.read().as_str(), "");
+            let root = &txn.root;
+            assert_eq!(root.prefix.as_slice(), b"");
 
-            let edge = root.get_edge(b'0');
+            let edge = root.get_edge(&b'0', &Binary);
             assert!(edge.is_some());
             let (_, child_node) = edge.unwrap();
             assert_eq!(
                 *child_node,
                 Node::new_with_edges(
-                    "00",
+                    b"00",
                     None,
                     vec![
-                        Edge::new(b'1', Node::new("1", LeafNode::new("001", 1).into()).into()),
-                        Edge::new(b'2', Node::new("2", LeafNode::new("002", 2).into()).into()),
-                        Edge::new(b'3', Node::new("3", LeafNode::new("003", 3).into()).into()),
+                        Edge::new(
+                            b'1',
+                            Node::new(b"1", LeafNode::new(b"001", 1).into()).into()
+                        ),
+                        Edge::new(
+                            b'2',
+                            Node::new(b"2", LeafNode::new(b"002", 2).into()).into()
+                        ),
+                        Edge::new(
+                            b'3',
+                            Node::new(b"3", LeafNode::new(b"003", 3).into()).into()
+                        ),
                     ]
                 )
             );
@@ -530,35 +1313,35 @@ mod tests {
             let result = txn.insert("010", 10);
             assert!(result.is_none());
             assert_eq!(txn.size.load(atomic::Ordering::Relaxed), 4);
-            let root = txn.root.read();
-            assert_eq!(root.prefix.read().as_str(), "");
+            let root = &txn.root;
+            assert_eq!(root.prefix.as_slice(), b"");
 
-            let edge = root.get_edge(b'0');
+            let edge = root.get_edge(&b'0', &Binary);
             assert!(edge.is_some());
             let (_, child_node) = edge.unwrap();
             assert_eq!(
                 *child_node,
                 Node::new_with_edges(
-                    "0",
+                    b"0",
                     None,
                     vec![
                         Edge::new(
                             b'0',
                             Node::new_with_edges(
-                                "0",
+                                b"0",
                                 None,
                                 vec![
                                     Edge::new(
                                         b'1',
-                                        Node::new("1", LeafNode::new("001", 1).into()).into()
+                                        Node::new(b"1", LeafNode::new(b"001", 1).into()).into()
                                     ),
                                     Edge::new(
                                         b'2',
-                                        Node::new("2", LeafNode::new("002", 2).into()).into()
+                                        Node::new(b"2", LeafNode::new(b"002", 2).into()).into()
                                     ),
                                     Edge::new(
                                         b'3',
-                                        Node::new("3", LeafNode::new("003", 3).into()).into()
+                                        Node::new(b"3", LeafNode::new(b"003", 3).into()).into()
                                     ),
                                 ]
                             )
@@ -566,7 +1349,7 @@ mod tests {
                         ),
                         Edge::new(
                             b'1',
-                            Node::new("10", LeafNode::new("010", 10).into()).into()
+                            Node::new(b"10", LeafNode::new(b"010", 10).into()).into()
                         ),
                     ]
                 )
@@ -578,35 +1361,35 @@ mod tests {
             let result = txn.insert("100", 100);
             assert!(result.is_none());
             assert_eq!(txn.size.load(atomic::Ordering::Relaxed), 5);
-            let root = txn.root.read();
-            assert_eq!(root.prefix.read().as_str(), "");
+            let root = &txn.root;
+            assert_eq!(root.prefix.as_slice(), b"");
 
-            let edge_0 = root.get_edge(b'0');
+            let edge_0 = root.get_edge(&b'0', &Binary);
             assert!(edge_0.is_some());
             let (_, child_node) = edge_0.unwrap();
             assert_eq!(
                 *child_node,
                 Node::new_with_edges(
-                    "0",
+                    b"0",
                     None,
                     vec![
                         Edge::new(
                             b'0',
                             Node::new_with_edges(
-                                "0",
+                                b"0",
                                 None,
                                 vec![
                                     Edge::new(
                                         b'1',
-                                        Node::new("1", LeafNode::new("001", 1).into()).into()
+                                        Node::new(b"1", LeafNode::new(b"001", 1).into()).into()
                                     ),
                                     Edge::new(
                                         b'2',
-                                        Node::new("2", LeafNode::new("002", 2).into()).into()
+                                        Node::new(b"2", LeafNode::new(b"002", 2).into()).into()
                                     ),
                                     Edge::new(
                                         b'3',
-                                        Node::new("3", LeafNode::new("003", 3).into()).into()
+                                        Node::new(b"3", LeafNode::new(b"003", 3).into()).into()
                                     ),
                                 ],
                             )
@@ -614,18 +1397,18 @@ mod tests {
                         ),
                         Edge::new(
                             b'1',
-                            Node::new("10", LeafNode::new("010", 10).into()).into()
+                            Node::new(b"10", LeafNode::new(b"010", 10).into()).into()
                         ),
                     ]
                 ),
             );
 
-            let edge_1 = root.get_edge(b'1');
+            let edge_1 = root.get_edge(&b'1', &Binary);
             assert!(edge_1.is_some());
             let (_, child_node) = edge_1.unwrap();
             assert_eq!(
                 *child_node,
-                Node::new("100", LeafNode::new("100", 100).into()).into(),
+                Node::new(b"100", LeafNode::new(b"100", 100).into()),
             );
         }
 
@@ -635,35 +1418,35 @@ mod tests {
             assert!(result.is_some());
             assert_eq!(result.unwrap(), 2);
             assert_eq!(txn.size.load(atomic::Ordering::Relaxed), 5);
-            let root = txn.root.read();
-            assert_eq!(root.prefix.read().as_str(), "");
+            let root = &txn.root;
+            assert_eq!(root.prefix.as_slice(), b"");
 
-            let edge_0 = root.get_edge(b'0');
+            let edge_0 = root.get_edge(&b'0', &Binary);
             assert!(edge_0.is_some());
             let (_, child_node) = edge_0.unwrap();
             assert_eq!(
                 *child_node,
                 Node::new_with_edges(
-                    "0",
+                    b"0",
                     None,
                     vec![
                         Edge::new(
                             b'0',
                             Node::new_with_edges(
-                                "0",
+                                b"0",
                                 None,
                                 vec![
                                     Edge::new(
                                         b'1',
-                                        Node::new("1", LeafNode::new("001", 1).into()).into()
+                                        Node::new(b"1", LeafNode::new(b"001", 1).into()).into()
                                     ),
                                     Edge::new(
                                         b'2',
-                                        Node::new("2", LeafNode::new("002", 20).into()).into()
+                                        Node::new(b"2", LeafNode::new(b"002", 20).into()).into()
                                     ),
                                     Edge::new(
                                         b'3',
-                                        Node::new("3", LeafNode::new("003", 3).into()).into()
+                                        Node::new(b"3", LeafNode::new(b"003", 3).into()).into()
                                     ),
                                 ]
                             )
@@ -671,7 +1454,7 @@ mod tests {
                         ),
                         Edge::new(
                             b'1',
-                            Node::new("10", LeafNode::new("010", 10).into()).into()
+                            Node::new(b"10", LeafNode::new(b"010", 10).into()).into()
                         )
                     ]
                 )
@@ -684,15 +1467,15 @@ mod tests {
             assert!(result.is_some());
             assert_eq!(result.unwrap(), 100);
             assert_eq!(txn.size.load(atomic::Ordering::Relaxed), 5);
-            let root = txn.root.read();
-            assert_eq!(root.prefix.read().as_str(), "");
+            let root = &txn.root;
+            assert_eq!(root.prefix.as_slice(), b"");
 
-            let edge_1 = root.get_edge(b'1');
+            let edge_1 = root.get_edge(&b'1', &Binary);
             assert!(edge_1.is_some());
             let (_, child_node) = edge_1.unwrap();
             assert_eq!(
                 *child_node,
-                Node::new("100", LeafNode::new("100", 200).into()).into(),
+                Node::new(b"100", LeafNode::new(b"100", 200).into()),
             );
         }
     }
@@ -702,7 +1485,7 @@ mod tests {
         let tree = Tree::<bool>::new();
         let mut txn = tree.start_transaction();
 
-        let mock_keys = vec!["", "001", "002", "003", "010", "100"];
+        let mock_keys = ["", "001", "002", "003", "010", "100"];
 
         // setup initial keys
         for key in mock_keys.iter() {
@@ -716,7 +1499,7 @@ mod tests {
             let old_size = txn.size.load(atomic::Ordering::Relaxed);
             let result = txn.delete(key);
             assert!(result.is_some());
-            assert_eq!(result.unwrap(), true);
+            assert!(result.unwrap());
             assert_eq!(txn.size.load(atomic::Ordering::Relaxed), old_size - 1);
 
             // delete the second time, should be no-op
@@ -734,7 +1517,7 @@ mod tests {
             // prefix not a node in tree
             let tree = Tree::<bool>::new();
             let mut txn = tree.start_transaction();
-            let mock_keys = vec!["", "test/test1", "test/test2", "test/test3", "R", "RA"];
+            let mock_keys = ["", "test/test1", "test/test2", "test/test3", "R", "RA"];
             for key in mock_keys.iter() {
                 let result = txn.insert(key, true);
                 assert!(result.is_none());
@@ -754,7 +1537,7 @@ mod tests {
             // prefix is a node in tree
             let tree = Tree::<bool>::new();
             let mut txn = tree.start_transaction();
-            let mock_keys = vec![
+            let mock_keys = [
                 "",
                 "test",
                 "test/test1",
@@ -783,7 +1566,7 @@ mod tests {
             // longer prefix and not a node in tree
             let tree = Tree::<bool>::new();
             let mut txn = tree.start_transaction();
-            let mock_keys = vec![
+            let mock_keys = [
                 "",
                 "test/test1",
                 "test/test2",
@@ -811,7 +1594,7 @@ mod tests {
             // prefix match single node
             let tree = Tree::<bool>::new();
             let mut txn = tree.start_transaction();
-            let mock_keys = vec!["", "AB", "ABC", "AR", "R", "RA"];
+            let mock_keys = ["", "AB", "ABC", "AR", "R", "RA"];
             for key in mock_keys.iter() {
                 let result = txn.insert(key, true);
                 assert!(result.is_none());
@@ -835,37 +1618,37 @@ mod tests {
         let mut txn = tree.start_transaction();
 
         // construct a node with single child
-        let parent_node = Node::new("parent", None);
+        let mut parent_node = Node::new(b"parent", None);
 
         let child_node = Arc::new(Node::new_with_edges(
-            "child",
-            LeafNode::new("child_key", 42).into(),
+            b"child",
+            LeafNode::new(b"child_key", 42).into(),
             vec![Edge::new(
                 b'1',
-                Node::new("1", LeafNode::new("001", 1).into()).into(),
+                Node::new(b"1", LeafNode::new(b"001", 1).into()).into(),
             )],
         ));
 
-        parent_node.add_edge(Edge::new(b'c', child_node.clone()));
+        parent_node.add_edge(Edge::new(b'c', child_node.clone()), &Binary);
 
         // merge the child into the parent
-        txn.merge_child(&parent_node);
+        txn.writer().1.merge_child(&mut parent_node);
 
         // verify the parent node has been updated correctly
-        assert_eq!(parent_node.prefix.read().as_str(), "parentchild");
+        assert_eq!(parent_node.prefix.as_slice(), b"parentchild");
         assert!(parent_node.is_leaf());
 
-        let leaf = parent_node.leaf.read();
+        let leaf = &parent_node.leaf;
         assert!(leaf.is_some());
         let leaf = leaf.as_ref().unwrap();
-        assert_eq!(leaf.get_key(), "child_key");
+        assert_eq!(leaf.get_key(), b"child_key");
         assert_eq!(*leaf.get_value(), 42);
         assert_eq!(parent_node.edge_len(), 1);
         assert_eq!(
             parent_node.edges,
             vec![Edge::new(
                 b'1',
-                Node::new("1", LeafNode::new("001", 1).into()).into()
+                Node::new(b"1", LeafNode::new(b"001", 1).into()).into()
             )]
             .into()
         );
@@ -877,8 +1660,8 @@ mod tests {
         let tree = Tree::<u32>::new();
         let mut txn = tree.start_transaction();
 
-        let leaf_node = Node::new("leaf", LeafNode::new("leaf_key", 42).into());
-        txn.merge_child(&leaf_node);
+        let mut leaf_node = Node::new(b"leaf", LeafNode::new(b"leaf_key", 42).into());
+        txn.writer().1.merge_child(&mut leaf_node);
     }
 
     #[test]
@@ -887,11 +1670,11 @@ mod tests {
         let tree = Tree::<u32>::new();
         let mut txn = tree.start_transaction();
 
-        let parent_node = Node::new("parent", None);
+        let mut parent_node = Node::new(b"parent", None);
 
-        parent_node.add_edge(Edge::new(b'a', Node::default().into()));
-        parent_node.add_edge(Edge::new(b'b', Node::default().into()));
+        parent_node.add_edge(Edge::new(b'a', Node::default().into()), &Binary);
+        parent_node.add_edge(Edge::new(b'b', Node::default().into()), &Binary);
 
-        txn.merge_child(&parent_node);
+        txn.writer().1.merge_child(&mut parent_node);
     }
 }
@@ -1,6 +1,13 @@
 #[cfg(test)]
 mod tests {
-    use crate::{node::Node, tree::Tree};
+    use std::{cmp::Ordering, ops::Bound, sync::Arc};
+
+    use crate::{
+        aggregate::{Max, Sum},
+        collation::{Binary, CaseInsensitive, Collation, Natural},
+        node::{LeafNode, Node},
+        tree::{ChangeKind, ChangefeedEntry, Entry, MultiTree, NodeArena, Tree},
+    };
 
     #[test]
     fn test_new_tree() {
@@ -11,6 +18,11 @@ mod tests {
             Tree::<bool> {
                 root: Node::default().into(),
                 size: 0,
+                seq: 0,
+                collation: Arc::new(Binary),
+                monoid: None,
+                lineage: Arc::default(),
+                arena: None,
             }
         );
     }
@@ -22,7 +34,7 @@ mod tests {
         {
             // insert new key into transaction should not affect original tree
             let mut txn = tree.start_transaction();
-            let insert_keys = vec!["001", "002", "010", "100"];
+            let insert_keys = ["001", "002", "010", "100"];
             for key in insert_keys.iter() {
                 txn.insert(key, true);
             }
@@ -56,7 +68,7 @@ mod tests {
         {
             // delete key in transaction should not affect original tree
             let mut txn = tree.start_transaction();
-            let check_keys = vec!["002", "010", "100"];
+            let check_keys = ["002", "010", "100"];
 
             let result = txn.delete("001");
             assert_eq!(result, Some(true), "deleted value should be Some(true)");
@@ -92,7 +104,7 @@ mod tests {
             // delete prefix in transaction should not affect original tree
             let mut txn = tree.start_transaction();
 
-            let deleted_keys = vec!["002", "010"];
+            let deleted_keys = ["002", "010"];
             let check_key = "100";
 
             let result = txn.delete_prefix("0");
@@ -185,4 +197,1397 @@ mod tests {
         assert!(has_deleted, "should delete keys with prefix '00'");
         assert_eq!(tree.len(), 2, "tree size should be 2 after prefix deletion");
     }
+
+    #[test]
+    fn test_segment_keys() {
+        let path = |p: &str| p.split('/').map(String::from).collect::<Vec<_>>();
+
+        let tree = Tree::<u32, String>::new();
+        let (tree, _) = tree.insert(path("usr/local/bin"), 1);
+        let (tree, _) = tree.insert(path("usr/local/lib"), 2);
+        let (tree, _) = tree.insert(path("usr/lib"), 3);
+        assert_eq!(tree.len(), 3);
+
+        assert_eq!(tree.get(path("usr/local/bin")), Some(1));
+        assert_eq!(tree.get(path("usr/local/lib")), Some(2));
+        assert_eq!(tree.get(path("usr/lib")), Some(3));
+        assert_eq!(tree.get(path("usr/local")), None);
+        assert_eq!(tree.get(path("usr/li")), None, "segments match as a whole");
+
+        // the root branches per path component rather than per byte
+        let usr = tree.root().get_edge(&"usr".to_string(), &Binary).unwrap().1;
+        assert_eq!(usr.prefix.as_slice(), path("usr").as_slice());
+        assert_eq!(usr.edge_len(), 2);
+
+        let (tree, has_deleted) = tree.delete_prefix(path("usr/local"));
+        assert!(has_deleted);
+        assert_eq!(tree.len(), 1);
+        assert_eq!(tree.get(path("usr/lib")), Some(3));
+    }
+
+    #[test]
+    fn test_char_keys() {
+        let chars = |s: &str| s.chars().collect::<Vec<_>>();
+
+        let tree = Tree::<u32, char>::new();
+        let (tree, _) = tree.insert(chars("日本語"), 1);
+        let (tree, _) = tree.insert(chars("日本"), 2);
+        assert_eq!(tree.get(chars("日本語")), Some(1));
+        assert_eq!(tree.get(chars("日本")), Some(2));
+        assert_eq!(tree.get(chars("日")), None);
+    }
+
+    #[test]
+    fn test_case_insensitive_collation() {
+        let tree = Tree::<u32>::with_collation(CaseInsensitive);
+        let (tree, _) = tree.insert("Hello", 1);
+        let (tree, _) = tree.insert("WORLD", 2);
+        assert_eq!(tree.len(), 2);
+
+        assert_eq!(tree.get("hello"), Some(1));
+        assert_eq!(tree.get("HELLO"), Some(1));
+        assert_eq!(tree.get("World"), Some(2));
+
+        // the original key is preserved in the leaf
+        let root = tree.root();
+        let (key, value) = root.longest_prefix(b"world!", tree.collation()).unwrap();
+        assert_eq!((key.as_slice(), value), (b"WORLD".as_slice(), 2));
+
+        // keys that only differ by case refer to the same entry
+        let (tree, old_value) = tree.insert("hello", 10);
+        assert_eq!(old_value, Some(1));
+        assert_eq!(tree.len(), 2);
+        assert_eq!(tree.get("HeLLo"), Some(10));
+
+        let (tree, old_value) = tree.delete("HELLO");
+        assert_eq!(old_value, Some(10));
+        assert_eq!(tree.get("hello"), None);
+
+        let (tree, has_deleted) = tree.delete_prefix("wor");
+        assert!(has_deleted);
+        assert!(tree.is_empty());
+    }
+
+    #[test]
+    fn test_custom_collation_ordering() {
+        #[derive(Debug)]
+        struct Descending;
+
+        impl Collation<u8> for Descending {
+            fn compare(&self, a: &u8, b: &u8) -> Ordering {
+                b.cmp(a)
+            }
+        }
+
+        let mut txn = Tree::<u32>::with_collation(Descending).start_transaction();
+        for (i, key) in ["apple", "banana", "cherry", "avocado"].iter().enumerate() {
+            txn.insert(key, i as u32);
+        }
+        let tree = txn.commit();
+
+        for (i, key) in ["apple", "banana", "cherry", "avocado"].iter().enumerate() {
+            assert_eq!(tree.get(key), Some(i as u32));
+        }
+
+        let mut labels = Vec::new();
+        tree.root()
+            .for_each_edge(|edge| labels.push(edge.get_node().prefix[0]));
+        assert_eq!(labels, vec![b'c', b'b', b'a']);
+
+        let (key, _) = tree.root().minimum().unwrap();
+        assert_eq!(key, b"cherry");
+        let (key, _) = tree.root().maximum().unwrap();
+        assert_eq!(key, b"apple");
+    }
+
+    #[test]
+    fn test_natural_collation() {
+        let mut txn = Tree::<u32>::with_collation(Natural).start_transaction();
+        for (i, key) in ["file10", "file2", "file1", "file100", "file20"]
+            .iter()
+            .enumerate()
+        {
+            txn.insert(key, i as u32);
+        }
+        let tree = txn.commit();
+
+        assert_eq!(tree.get("file2"), Some(1));
+        assert_eq!(tree.get("file02"), None);
+        assert_eq!(tree.get("file100"), Some(3));
+
+        assert_eq!(tree.minimum(), Some((b"file1".to_vec(), 2)));
+        assert_eq!(tree.maximum(), Some((b"file100".to_vec(), 3)));
+
+        let (tree, _) = tree.delete("file1");
+        assert_eq!(tree.minimum(), Some((b"file2".to_vec(), 1)));
+        let (tree, _) = tree.delete("file100");
+        assert_eq!(tree.maximum(), Some((b"file20".to_vec(), 4)));
+    }
+
+    #[test]
+    fn test_values_without_eq_hash_or_default() {
+        let tree = Tree::<f64>::new();
+        let (tree, _) = tree.insert("pi", std::f64::consts::PI);
+        let (tree, _) = tree.insert("nan", f64::NAN);
+        assert_eq!(tree.get("pi"), Some(std::f64::consts::PI));
+        assert!(tree.get("nan").unwrap().is_nan());
+
+        type Handler = Arc<dyn Fn(u32) -> u32 + Send + Sync>;
+        let tree = Tree::<Handler>::new();
+        let (tree, _) = tree.insert("double", Arc::new(|x: u32| x * 2) as Handler);
+        let (tree, _) = tree.insert("square", Arc::new(|x: u32| x * x) as Handler);
+        let (tree, old_handler) = tree.delete("double");
+        assert_eq!(old_handler.map(|f| f(21)), Some(42));
+        assert_eq!(tree.get("square").map(|f| f(3)), Some(9));
+        assert!(tree.get("double").is_none());
+    }
+
+    fn keys<T: Clone>(leaves: impl Iterator<Item = Arc<LeafNode<T>>>) -> Vec<String> {
+        leaves
+            .map(|leaf| String::from_utf8(leaf.get_key().to_vec()).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_get_ref() {
+        let tree = Tree::<Vec<u8>>::new();
+        let (tree, _) = tree.insert("blob", vec![7; 1024]);
+
+        let first = tree.get_ref("blob").unwrap();
+        let second = tree.get_ref("blob").unwrap();
+        assert!(Arc::ptr_eq(&first, &second), "leaf should be shared");
+        assert_eq!(first.get_key(), b"blob");
+        assert_eq!(first.get_value().len(), 1024);
+        assert!(tree.get_ref("missing").is_none());
+
+        assert_eq!(tree.with_value("blob", |value| value.len()), Some(1024));
+        assert_eq!(tree.with_value("missing", |value| value.len()), None);
+
+        let mut txn = tree.start_transaction();
+        txn.insert("other", vec![1]);
+        assert_eq!(txn.with_value("other", |value| value[0]), Some(1));
+        assert_eq!(txn.get_ref("blob").unwrap().get_value().len(), 1024);
+    }
+
+    #[test]
+    fn test_entry() {
+        let tree = Tree::<u32>::new();
+        let (tree, _) = tree.insert("foo", 1);
+        let mut txn = tree.start_transaction();
+
+        // count the words through the entry api
+        for word in ["foo", "foobar", "fo", "foo", "bar", "foobar", "foo"] {
+            txn.entry(word).and_modify(|count| *count += 1).or_insert(1);
+        }
+        assert_eq!(txn.len(), 4);
+        assert_eq!(txn.get("foo"), Some(4));
+        assert_eq!(txn.get("foobar"), Some(2));
+        assert_eq!(txn.get("fo"), Some(1));
+        assert_eq!(txn.get("bar"), Some(1));
+
+        let leaf = txn.entry("baz").or_insert_with(|| 7);
+        assert_eq!(leaf.get_key(), b"baz");
+        assert_eq!(*leaf.get_value(), 7);
+        let leaf = txn.entry("baz").or_insert_with(|| unreachable!());
+        assert_eq!(*leaf.get_value(), 7);
+        assert_eq!(txn.len(), 5);
+
+        match txn.entry("foo") {
+            Entry::Occupied(mut entry) => {
+                assert_eq!(entry.key(), b"foo");
+                assert_eq!(entry.insert(10), 4);
+                assert_eq!(*entry.get(), 10);
+                assert_eq!(entry.remove(), 10);
+            }
+            Entry::Vacant(_) => panic!("key 'foo' should be occupied"),
+        }
+        assert_eq!(txn.get("foo"), None);
+        assert_eq!(txn.get("foobar"), Some(2));
+        assert_eq!(txn.get("fo"), Some(1));
+        assert_eq!(txn.len(), 4);
+
+        match txn.entry("foobar") {
+            Entry::Occupied(entry) => assert_eq!(entry.remove(), 2),
+            Entry::Vacant(_) => panic!("key 'foobar' should be occupied"),
+        }
+        match txn.entry("missing") {
+            Entry::Occupied(_) => panic!("key 'missing' should be vacant"),
+            Entry::Vacant(entry) => assert_eq!(entry.key(), b"missing"),
+        }
+        assert_eq!(txn.len(), 3);
+
+        let new_tree = txn.commit();
+        let keys: Vec<_> = new_tree
+            .iter()
+            .map(|leaf| leaf.get_key().to_vec())
+            .collect();
+        assert_eq!(keys, vec![b"bar".to_vec(), b"baz".to_vec(), b"fo".to_vec()]);
+        assert_eq!(new_tree.len(), 3);
+
+        // the entries should not affect the original tree
+        assert_eq!(tree.len(), 1);
+        assert_eq!(tree.get("foo"), Some(1));
+        assert_eq!(tree.get("bar"), None);
+    }
+
+    #[test]
+    fn test_entry_root_and_collation() {
+        let mut txn = Tree::<u32>::with_collation(CaseInsensitive).start_transaction();
+        txn.entry("").or_insert(1);
+        txn.entry("Key").or_insert(2);
+        txn.entry("KEY")
+            .and_modify(|value| *value += 1)
+            .or_insert(0);
+        assert_eq!(txn.get(""), Some(1));
+        assert_eq!(txn.get("key"), Some(3));
+        assert_eq!(txn.get_ref("key").unwrap().get_key(), b"Key");
+
+        match txn.entry("") {
+            Entry::Occupied(entry) => assert_eq!(entry.remove(), 1),
+            Entry::Vacant(_) => panic!("empty key should be occupied"),
+        }
+        assert_eq!(txn.get(""), None);
+        assert_eq!(txn.len(), 1);
+    }
+
+    #[test]
+    fn test_conditional_mutations() {
+        let (tree, _) = Tree::<u32>::new().insert("foo", 1);
+        let (tree, _) = tree.insert("foobar", 2);
+
+        let mut txn = tree.start_transaction();
+        let root = txn.root();
+
+        // failed conditions should not copy any node
+        assert!(!txn.insert_if_absent("foo", 10));
+        assert!(!txn.compare_and_swap("foo", &10, 20));
+        assert!(!txn.compare_and_swap("missing", &1, 20));
+        assert_eq!(txn.delete_if("foo", |value| *value > 1), None);
+        assert_eq!(txn.delete_if("missing", |_| true), None);
+        assert_eq!(txn.update("missing", |_| None), None);
+        assert!(Arc::ptr_eq(&root, &txn.root()), "root should not be copied");
+        assert_eq!(txn.cloned_nodes(), 0, "no node should be copied");
+
+        assert!(txn.insert_if_absent("bar", 3));
+        assert!(txn.compare_and_swap("foo", &1, 10));
+        assert_eq!(txn.delete_if("foobar", |value| *value == 2), Some(2));
+        assert_eq!(txn.update("bar", |value| value.map(|v| v * 2)), Some(3));
+        assert_eq!(
+            txn.update("baz", |value| Some(value.map_or(5, |v| v + 1))),
+            None
+        );
+        assert_eq!(txn.len(), 3);
+        assert_eq!(txn.get("foo"), Some(10));
+        assert_eq!(txn.get("foobar"), None);
+        assert_eq!(txn.get("bar"), Some(6));
+        assert_eq!(txn.get("baz"), Some(5));
+
+        assert_eq!(txn.update("bar", |_| None), Some(6));
+        assert_eq!(txn.get("bar"), None);
+        assert_eq!(txn.len(), 2);
+
+        // the tree methods should return the same tree when nothing changes
+        let root = tree.root();
+        let (tree, inserted) = tree.insert_if_absent("foo", 10);
+        assert!(!inserted);
+        let (tree, swapped) = tree.compare_and_swap("foo", &10, 20);
+        assert!(!swapped);
+        let (tree, deleted) = tree.delete_if("foobar", |value| *value != 2);
+        assert_eq!(deleted, None);
+        let (tree, old_value) = tree.update("missing", |_| None);
+        assert_eq!(old_value, None);
+        assert!(Arc::ptr_eq(&root, &tree.root()), "tree should be untouched");
+
+        let (tree, inserted) = tree.insert_if_absent("bar", 3);
+        assert!(inserted);
+        let (tree, swapped) = tree.compare_and_swap("foo", &1, 10);
+        assert!(swapped);
+        let (tree, deleted) = tree.delete_if("foobar", |value| *value == 2);
+        assert_eq!(deleted, Some(2));
+        let (tree, old_value) = tree.update("bar", |value| value.map(|v| v + 1));
+        assert_eq!(old_value, Some(3));
+        let (tree, old_value) = tree.update("foo", |_| None);
+        assert_eq!(old_value, Some(10));
+        assert_eq!(tree.len(), 1);
+        assert_eq!(tree.get("bar"), Some(4));
+    }
+
+    #[test]
+    fn test_multimap() {
+        let tree = MultiTree::<u64>::new();
+        let (tree, inserted) = tree.insert_value("tag/red", 3);
+        assert!(inserted);
+        let (tree, _) = tree.insert_value("tag/red", 1);
+        let (tree, _) = tree.insert_value("tag/blue", 2);
+        let (tree, inserted) = tree.insert_value("tag/red", 3);
+        assert!(!inserted, "duplicate value should not be added");
+        assert_eq!(tree.len(), 2);
+        assert_eq!(tree.get_all("tag/red").to_vec(), [1, 3]);
+        assert!(tree.get_all("tag/green").is_empty());
+
+        let mut txn = tree.start_transaction();
+        assert!(txn.insert_value("tag/red", 2));
+        assert!(txn.insert_value("tag/green", 4));
+        assert!(txn.remove_value("tag/blue", &2));
+        assert!(!txn.remove_value("tag/blue", &2));
+        assert!(!txn.remove_value("tag/red", &7));
+        assert_eq!(txn.len(), 2, "key should be removed with its last value");
+        assert_eq!(txn.get_all("tag/red").to_vec(), [1, 2, 3]);
+        let new_tree = txn.commit();
+
+        // the transaction should not affect the original tree
+        assert_eq!(tree.get_all("tag/red").to_vec(), [1, 3]);
+        assert_eq!(tree.get_all("tag/blue").to_vec(), [2]);
+
+        let pairs: Vec<_> = new_tree.iter().collect();
+        assert_eq!(
+            pairs,
+            vec![
+                (Arc::from(*b"tag/green"), 4),
+                (Arc::from(*b"tag/red"), 1),
+                (Arc::from(*b"tag/red"), 2),
+                (Arc::from(*b"tag/red"), 3),
+            ]
+        );
+        assert!(
+            Arc::ptr_eq(&pairs[1].0, &pairs[3].0),
+            "pairs of a key share it"
+        );
+        assert_eq!(new_tree.iter_prefix("tag/r").count(), 3);
+
+        let (new_tree, removed) = new_tree.remove_value("tag/green", &4);
+        assert!(removed);
+        let (new_tree, removed) = new_tree.remove_value("tag/green", &4);
+        assert!(!removed);
+        assert_eq!(new_tree.len(), 1);
+        assert_eq!(new_tree.as_tree().get("tag/red").unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_multimap_many_values() {
+        // values added in a scrambled order stay sorted
+        let mut txn = MultiTree::<u32>::new().start_transaction();
+        for i in 0..1000 {
+            assert!(txn.insert_value("tag", i * 7919 % 1000));
+        }
+        let tree = txn.commit();
+        let values = tree.get_all("tag");
+        assert_eq!(values.len(), 1000);
+        assert_eq!(values.to_vec(), (0..1000).collect::<Vec<_>>());
+        assert_eq!(values.get(500), Some(&500));
+        assert_eq!(values.get(1000), None);
+        assert!(values.contains(&999));
+
+        // removing values leaves the old version untouched
+        let mut txn = tree.start_transaction();
+        for i in (0..1000).filter(|i| i % 3 != 0) {
+            assert!(txn.remove_value("tag", &i));
+        }
+        assert!(!txn.remove_value("tag", &1));
+        let new_tree = txn.commit();
+        let new_values = new_tree.get_all("tag");
+        assert_eq!(
+            new_values.to_vec(),
+            (0..1000).step_by(3).collect::<Vec<_>>()
+        );
+        assert_eq!(new_values.iter().len(), 334);
+        assert_eq!(new_tree.iter().count(), 334);
+        assert_eq!(tree.get_all("tag"), values);
+        assert_ne!(new_values, values);
+        assert_eq!(
+            format!(
+                "{:?}",
+                MultiTree::<u32>::new().insert_value("k", 1).0.get_all("k")
+            ),
+            "[1]"
+        );
+    }
+
+    #[test]
+    fn test_aggregate_prefix() {
+        let tree = Tree::<u64>::new().with_aggregate(Sum);
+        let mut txn = tree.start_transaction();
+        txn.insert("home/alice/docs", 10);
+        txn.insert("home/alice/music", 20);
+        txn.insert("home/bob", 5);
+        txn.insert("home", 1);
+        txn.insert("var/log", 7);
+        assert_eq!(txn.aggregate_prefix("home/alice"), Some(30));
+        let tree = txn.commit();
+
+        assert_eq!(tree.aggregate_prefix(""), Some(43));
+        assert_eq!(tree.aggregate_prefix("home"), Some(36));
+        assert_eq!(tree.aggregate_prefix("home/"), Some(35));
+        assert_eq!(tree.aggregate_prefix("home/alice"), Some(30));
+        assert_eq!(tree.aggregate_prefix("home/al"), Some(30));
+        assert_eq!(tree.aggregate_prefix("home/alice/docs"), Some(10));
+        assert_eq!(tree.aggregate_prefix("home/carol"), None);
+        assert_eq!(tree.aggregate_prefix("home/alice/docs/x"), None);
+
+        let (tree, _) = tree.insert("home/alice/docs", 15);
+        assert_eq!(tree.aggregate_prefix("home/alice"), Some(35));
+        let (tree, _) = tree.delete("home/alice/music");
+        assert_eq!(tree.aggregate_prefix("home/alice"), Some(15));
+        assert_eq!(tree.aggregate_prefix("home"), Some(21));
+        let (tree, _) = tree.delete_prefix("home/");
+        assert_eq!(tree.aggregate_prefix("home"), Some(1));
+        assert_eq!(tree.aggregate_prefix(""), Some(8));
+
+        let mut txn = tree.start_transaction();
+        txn.entry("var/log").and_modify(|usage| *usage += 3);
+        txn.entry("var/tmp").or_insert(4);
+        assert_eq!(txn.aggregate_prefix("var"), Some(14));
+        match txn.entry("home") {
+            Entry::Occupied(entry) => assert_eq!(entry.remove(), 1),
+            Entry::Vacant(_) => panic!("key 'home' should be occupied"),
+        }
+        let tree = txn.commit();
+        assert_eq!(tree.aggregate_prefix(""), Some(14));
+        assert_eq!(tree.aggregate_prefix("home"), None);
+
+        // trees without an aggregate have no summaries
+        let (tree, _) = Tree::<u64>::new().insert("foo", 1);
+        assert_eq!(tree.aggregate_prefix(""), None);
+
+        // an aggregate added to a non-empty tree covers its existing keys
+        let (tree, _) = tree.insert("foo/bar", 2);
+        let (tree, _) = tree.insert("baz", 4);
+        let aggregated = tree.clone().with_aggregate(Sum);
+        assert_eq!(aggregated.aggregate_prefix(""), Some(7));
+        assert_eq!(aggregated.aggregate_prefix("foo"), Some(3));
+        assert_eq!(tree.aggregate_prefix(""), None);
+        let (aggregated, _) = aggregated.insert("foo/qux", 8);
+        assert_eq!(aggregated.aggregate_prefix("foo"), Some(11));
+        assert_eq!(
+            aggregated.with_aggregate(Max).aggregate_prefix("foo"),
+            Some(8)
+        );
+    }
+
+    #[test]
+    fn test_aggregate_matches_scan() {
+        let mut tree = Tree::<u32>::new().with_aggregate(Max);
+        let mut seed = 7u32;
+        let mut next = || {
+            seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+            (seed >> 16) % 1000
+        };
+        for round in 0..400 {
+            let key = format!("{:03}", next());
+            tree = match round % 7 {
+                5 => tree.delete(&key).0,
+                6 => tree.delete_prefix(&key[..2]).0,
+                _ => tree.insert(&key, next()).0,
+            };
+
+            for prefix in ["", "1", "2", "55", "9", &key[..1], &key[..2], &key] {
+                let expected = tree.iter_prefix(prefix).map(|leaf| *leaf.get_value()).max();
+                assert_eq!(tree.aggregate_prefix(prefix), expected, "prefix '{prefix}'");
+            }
+        }
+    }
+
+    #[test]
+    fn test_map_filter_retain() {
+        let insert_keys = [
+            "", "a", "ab", "abc", "abd", "b", "ba", "bab", "bb", "foo/bar", "foo/baz", "zip",
+        ];
+        let mut txn = Tree::<u32>::new().start_transaction();
+        for (i, key) in insert_keys.iter().enumerate() {
+            txn.insert(key, i as u32);
+        }
+        let tree = txn.commit();
+
+        let mapped = tree.map(|value| format!("#{value}"));
+        assert_eq!(mapped.len(), tree.len());
+        for (i, key) in insert_keys.iter().enumerate() {
+            assert_eq!(mapped.get(key), Some(format!("#{i}")));
+        }
+
+        let even = tree.map(|value| *value).filter(|_, value| value % 2 == 0);
+        let mut rebuilt = Tree::<u32>::new();
+        for (i, key) in insert_keys.iter().enumerate().filter(|(i, _)| i % 2 == 0) {
+            rebuilt = rebuilt.insert(key, i as u32).0;
+        }
+        assert_eq!(even.len(), 6);
+        assert_eq!(even, rebuilt, "retained nodes should be compacted");
+
+        let mut txn = tree.start_transaction();
+        let root = txn.root();
+        txn.retain(|_, _| true);
+        assert!(Arc::ptr_eq(&root, &txn.root()), "nothing should be copied");
+        txn.retain(|key, _| key != b"zip");
+        assert_eq!(txn.cloned_nodes(), 1, "only the root should be copied");
+        assert_eq!(txn.len(), 11);
+        txn.retain(|key, _| !key.starts_with(b"ab") && key != b"foo/bar");
+        assert_eq!(txn.len(), 7);
+        assert_eq!(txn.get("abc"), None);
+        assert_eq!(txn.get("a"), Some(1));
+        assert_eq!(txn.get("foo/baz"), Some(10));
+        txn.retain(|_, _| false);
+        assert_eq!(txn.len(), 0);
+        assert_eq!(txn.commit().iter().count(), 0);
+
+        // the original tree is untouched
+        assert_eq!(tree.len(), insert_keys.len() as u32);
+        assert_eq!(tree.get("abc"), Some(3));
+    }
+
+    #[test]
+    fn test_update_prefix() {
+        let mut txn = Tree::<bool>::new().with_aggregate(Max).start_transaction();
+        txn.insert("tenant/4", true);
+        txn.insert("tenant/42/a", true);
+        txn.insert("tenant/42/b", true);
+        txn.insert("tenant/420", true);
+        txn.insert("tenant/7/a", true);
+        let tree = txn.commit();
+
+        let mut txn = tree.start_transaction();
+        assert!(!txn.update_prefix("tenant/5", |_, _| false));
+        assert!(txn.update_prefix("tenant/42/", |_, _| false));
+        assert_eq!(txn.get("tenant/42/a"), Some(false));
+        assert_eq!(txn.get("tenant/42/b"), Some(false));
+        assert_eq!(txn.get("tenant/420"), Some(true));
+        assert_eq!(txn.get("tenant/4"), Some(true));
+        assert_eq!(txn.aggregate_prefix("tenant/42/"), Some(false));
+
+        // the prefix may end in the middle of a node prefix
+        assert!(txn.update_prefix("tenant/42", |key, value| *value || key.ends_with(b"b")));
+        assert_eq!(txn.get("tenant/42/a"), Some(false));
+        assert_eq!(txn.get("tenant/42/b"), Some(true));
+        assert_eq!(txn.get("tenant/420"), Some(true));
+        assert_eq!(txn.len(), 5);
+
+        assert_eq!(txn.aggregate_prefix("tenant/42/"), Some(true));
+
+        // the original tree is untouched
+        assert_eq!(tree.get("tenant/42/a"), Some(true));
+    }
+
+    #[test]
+    fn test_resolve() {
+        type Config = Vec<(&'static str, &'static str)>;
+        let merge = |mut inherited: Config, specific: &Config| {
+            for (name, value) in specific {
+                inherited.retain(|(n, _)| n != name);
+                inherited.push((name, value));
+            }
+            inherited.sort();
+            inherited
+        };
+
+        let mut txn = Tree::<Config>::new().start_transaction();
+        txn.insert("", vec![("log", "info"), ("region", "eu")]);
+        txn.insert("prod", vec![("replicas", "3")]);
+        txn.insert("prod/api", vec![("log", "warn")]);
+        txn.insert("prod/api/v2", vec![("replicas", "5")]);
+        txn.insert("prod/db", vec![("region", "us")]);
+        let tree = txn.commit();
+
+        let keys: Vec<_> = tree
+            .iter_path("prod/api/v2/x")
+            .map(|leaf| leaf.get_key().to_vec())
+            .collect();
+        assert_eq!(
+            keys,
+            vec![
+                b"".to_vec(),
+                b"prod".to_vec(),
+                b"prod/api".to_vec(),
+                b"prod/api/v2".to_vec()
+            ]
+        );
+
+        assert_eq!(
+            tree.resolve("prod/api/v2", merge),
+            Some(vec![("log", "warn"), ("region", "eu"), ("replicas", "5")])
+        );
+        assert_eq!(
+            tree.resolve("prod/api/v1", merge),
+            Some(vec![("log", "warn"), ("region", "eu"), ("replicas", "3")])
+        );
+        assert_eq!(
+            tree.resolve("prod/db", merge),
+            Some(vec![("log", "info"), ("region", "us"), ("replicas", "3")])
+        );
+        assert_eq!(
+            tree.resolve("dev", merge),
+            Some(vec![("log", "info"), ("region", "eu")])
+        );
+
+        // a key ending in the middle of a node prefix only inherits from its ancestors
+        assert_eq!(tree.iter_path("pro").count(), 1);
+        let (tree, _) = tree.delete("");
+        assert_eq!(tree.resolve("dev", merge), None);
+    }
+
+    #[test]
+    fn test_watch() {
+        let mut txn = Tree::<u32>::new().start_transaction();
+        for (i, key) in ["foo", "foo/bar", "foo/baz", "zip"].iter().enumerate() {
+            txn.insert(key, i as u32);
+        }
+        let tree = txn.commit();
+
+        let (bar_watch, value) = tree.get_watch("foo/bar");
+        assert_eq!(value, Some(1));
+        let (baz_watch, _) = tree.get_watch("foo/baz");
+        let (missing_watch, value) = tree.get_watch("foo/qux");
+        assert_eq!(value, None);
+        let foo_watch = tree.watch_prefix("foo/");
+        let fo_watch = tree.watch_prefix("fo");
+        let zip_watch = tree.watch_prefix("zip");
+
+        // watches only fire once the transaction is committed
+        let mut txn = tree.start_transaction();
+        txn.insert("foo/bar", 10);
+        assert!(!bar_watch.is_fired());
+        let tree = txn.commit();
+        assert!(bar_watch.is_fired());
+        assert!(foo_watch.is_fired());
+        assert!(fo_watch.is_fired());
+        assert!(!baz_watch.is_fired(), "unchanged key should not fire");
+        assert!(!zip_watch.is_fired(), "unchanged prefix should not fire");
+
+        let (tree, _) = tree.insert("foo/qux", 3);
+        assert!(missing_watch.is_fired(), "inserted key should fire");
+        assert!(!baz_watch.is_fired());
+
+        let (baz_watch, _) = tree.get_watch("foo/baz");
+        let (bar_watch, _) = tree.get_watch("foo/bar");
+        let (tree, _) = tree.delete_prefix("foo/b");
+        assert!(baz_watch.is_fired(), "deleted key should fire");
+        assert!(bar_watch.is_fired(), "deleted key should fire");
+        assert!(!zip_watch.is_fired());
+
+        // changes rolled back to a savepoint should not fire
+        let mut txn = tree.start_transaction();
+        let savepoint = txn.savepoint();
+        txn.delete("zip");
+        txn.rollback_to(&savepoint);
+        let tree = txn.commit();
+        assert!(!zip_watch.is_fired());
+
+        let (zip_watch, _) = tree.get_watch("zip");
+        let waiter = std::thread::spawn(move || zip_watch.wait());
+        let (tree, _) = tree.delete("zip");
+        waiter.join().unwrap();
+        assert_eq!(tree.len(), 2);
+
+        // deleting a prefix fires the watches of the internal nodes under it
+        let mut txn = tree.start_transaction();
+        for key in ["c/1a", "c/1b", "c/2a", "c/2b"] {
+            txn.insert(key, 0);
+        }
+        let tree = txn.commit();
+        let prefix_watch = tree.watch_prefix("c/1");
+        let (missing_watch, _) = tree.get_watch("c/1c");
+        let (tree, _) = tree.delete_prefix("c/");
+        assert!(prefix_watch.is_fired(), "deleted prefix should fire");
+        assert!(
+            missing_watch.is_fired(),
+            "missing key under a deleted prefix should fire"
+        );
+        assert_eq!(tree.len(), 2);
+    }
+
+    #[test]
+    fn test_watch_dropped_tree() {
+        let mut txn = Tree::<u32>::new().start_transaction();
+        for (i, key) in ["foo/bar", "foo/baz", "zip"].iter().enumerate() {
+            txn.insert(key, i as u32);
+        }
+        let tree = txn.commit();
+        let (missing_watch, _) = tree.get_watch("foo/qux");
+        let foo_watch = tree.watch_prefix("foo/");
+        let zip_watch = tree.watch_prefix("zip");
+
+        // once the tree is dropped its nodes are modified in place, but still signalled
+        let mut txn = tree.start_transaction();
+        drop(tree);
+        txn.insert("foo/qux", 3);
+        let tree = txn.commit();
+        assert!(missing_watch.is_fired(), "inserted key should fire");
+        assert!(foo_watch.is_fired(), "changed prefix should fire");
+        assert!(!zip_watch.is_fired(), "unchanged prefix should not fire");
+
+        // the renewed watches of the modified nodes fire again
+        let foo_watch = tree.watch_prefix("foo/");
+        let mut txn = tree.start_transaction();
+        drop(tree);
+        txn.delete("foo/bar");
+        txn.commit();
+        assert!(foo_watch.is_fired());
+    }
+
+    #[test]
+    fn test_watch_no_op() {
+        let tree = Tree::<u32>::new();
+        let (watch, _) = tree.get_watch("a");
+        let mut txn = tree.start_transaction();
+        assert!(!txn.delete_prefix(""));
+        assert!(!txn.update_prefix("", |_, value| value + 1));
+        assert_eq!(txn.cloned_nodes(), 0, "nothing should be copied");
+        let tree = txn.commit();
+        assert!(!watch.is_fired(), "a no-op delete_prefix should not fire");
+
+        let (tree, _) = tree.insert("b", 1);
+        let watch = tree.watch_prefix("b");
+        let (tree, deleted) = tree.delete_prefix("a");
+        assert!(!deleted);
+        assert!(!watch.is_fired());
+        assert_eq!(tree.len(), 1);
+    }
+
+    #[test]
+    fn test_watch_lazy() {
+        let (tree, _) = Tree::<u32>::new().insert("foo/bar", 1);
+        let (tree, _) = tree.insert("foo/baz", 2);
+        assert!(
+            tree.root.signal.get().is_none(),
+            "unwatched nodes should not allocate a watch"
+        );
+
+        // a watch taken from a replaced version once the change is committed has fired already
+        let (new_tree, _) = tree.clone().insert("foo/bar", 10);
+        assert!(tree.get_watch("foo/bar").0.is_fired());
+        assert!(tree.watch_prefix("foo/").is_fired());
+        assert!(!new_tree.get_watch("foo/bar").0.is_fired());
+        assert!(
+            !tree.get_watch("foo/baz").0.is_fired(),
+            "unchanged key should not fire"
+        );
+    }
+
+    #[test]
+    fn test_changefeed() {
+        let (tree, _) = Tree::<u32>::new().insert("a", 1);
+        assert_eq!(
+            tree.seq(),
+            1,
+            "commits before subscribing still take a sequence number"
+        );
+        let subscriber = tree.subscribe();
+        let other = tree.subscribe();
+        drop(other);
+
+        let (tree, _) = tree.insert("b", 2);
+        let (tree, _) = tree.insert_if_absent("b", 3);
+        let mut txn = tree.start_transaction();
+        txn.track_mutations(false);
+        txn.insert("a", 10);
+        txn.delete("b");
+        let (tree, changes) = txn.commit_with_changes();
+        assert!(
+            changes.is_none(),
+            "changes published to subscribers are not returned once tracking is disabled"
+        );
+        let (tree, _) = tree.delete("missing");
+        assert_eq!(tree.seq(), 3, "commits without changes keep the version");
+        let (tree, _) = tree.delete_prefix("");
+
+        let entries: Vec<_> = subscriber.try_iter().collect();
+        assert_eq!(
+            entries.iter().map(|entry| entry.seq()).collect::<Vec<_>>(),
+            vec![2, 3, 4],
+            "commits without changes should not be published"
+        );
+        assert_eq!(
+            entries
+                .iter()
+                .map(|entry| entry.parent_seq())
+                .collect::<Vec<_>>(),
+            vec![1, 2, 3]
+        );
+        assert_eq!(tree.seq(), 4);
+        let kinds = |entry: &ChangefeedEntry<u32>| {
+            entry
+                .changes()
+                .iter()
+                .map(|change| (change.key().to_vec(), change.kind()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            kinds(&entries[0]),
+            vec![(b"b".to_vec(), ChangeKind::Inserted)]
+        );
+        assert_eq!(
+            kinds(&entries[1]),
+            vec![
+                (b"a".to_vec(), ChangeKind::Updated),
+                (b"b".to_vec(), ChangeKind::Deleted),
+            ]
+        );
+        assert_eq!(
+            kinds(&entries[2]),
+            vec![(b"a".to_vec(), ChangeKind::Deleted)]
+        );
+
+        // subscribers receive commits from other threads in order
+        let handle = std::thread::spawn(move || {
+            let (tree, _) = tree.insert("c", 3);
+            tree.insert("d", 4).0
+        });
+        let tree = handle.join().unwrap();
+        let seqs: Vec<_> = subscriber.try_iter().map(|entry| entry.seq()).collect();
+        assert_eq!(seqs, vec![5, 6]);
+
+        // trees created separately do not share the lineage
+        let (_, _) = tree.map(|value| *value).insert("e", 5);
+        assert!(subscriber.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_changefeed_forks() {
+        let (tree, _) = Tree::<u32>::new().insert("a", 1);
+        let subscriber = tree.subscribe();
+
+        // both versions made on the same version name it as their parent
+        let (left, _) = tree.clone().insert("b", 2);
+        let (right, _) = tree.clone().insert("c", 3);
+        assert_ne!(left.seq(), right.seq());
+        let (child, _) = right.clone().insert("d", 4);
+
+        let entries: Vec<_> = subscriber
+            .try_iter()
+            .map(|entry| (entry.seq(), entry.parent_seq()))
+            .collect();
+        assert_eq!(
+            entries,
+            vec![
+                (left.seq(), tree.seq()),
+                (right.seq(), tree.seq()),
+                (child.seq(), right.seq()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_changefeed_open_transaction() {
+        let (tree, _) = Tree::<u32>::new().insert("a", 1);
+        let mut txn = tree.start_transaction();
+        txn.insert("b", 2);
+
+        // the transaction opened before subscribing is missed, but not silently
+        let subscriber = tree.subscribe();
+        let snapshot = tree.seq();
+        let tree = txn.commit();
+        assert!(subscriber.try_recv().is_err());
+        assert_ne!(tree.seq(), snapshot);
+
+        let (tree, _) = tree.insert("c", 3);
+        let entry = subscriber.try_recv().unwrap();
+        assert_eq!(entry.seq(), tree.seq());
+        assert_ne!(
+            entry.parent_seq(),
+            snapshot,
+            "the entry should not apply to the snapshot"
+        );
+    }
+
+    #[test]
+    fn test_old_versions_never_change() {
+        let snapshot = |tree: &Tree<u32>| {
+            tree.iter()
+                .map(|leaf| (leaf.get_key().to_vec(), *leaf.get_value()))
+                .collect::<Vec<_>>()
+        };
+
+        // equal subtrees under different parents, so an equality based lookup would confuse them
+        let mut txn = Tree::<u32>::new().start_transaction();
+        for key in ["a/x/1", "a/x/2", "b/x/1", "b/x/2", "c", "c/d"] {
+            txn.insert(key, 1);
+        }
+        let mut versions = vec![txn.commit()];
+
+        let mut txn = versions[0].start_transaction();
+        txn.insert("a/x/3", 2);
+        txn.insert("b/x/3", 2);
+        txn.delete("c");
+        versions.push(txn.commit());
+
+        let mut txn = versions[1].start_transaction();
+        txn.delete_prefix("a/");
+        txn.update_prefix("b/", |_, value| value + 10);
+        txn.entry("c/d").and_modify(|value| *value += 1);
+        versions.push(txn.commit());
+
+        let mut txn = versions[2].start_transaction();
+        txn.retain(|key, _| key.starts_with(b"b/x"));
+        txn.insert("b", 3);
+        versions.push(txn.commit());
+
+        let expected = [
+            vec![
+                (b"a/x/1".to_vec(), 1),
+                (b"a/x/2".to_vec(), 1),
+                (b"b/x/1".to_vec(), 1),
+                (b"b/x/2".to_vec(), 1),
+                (b"c".to_vec(), 1),
+                (b"c/d".to_vec(), 1),
+            ],
+            vec![
+                (b"a/x/1".to_vec(), 1),
+                (b"a/x/2".to_vec(), 1),
+                (b"a/x/3".to_vec(), 2),
+                (b"b/x/1".to_vec(), 1),
+                (b"b/x/2".to_vec(), 1),
+                (b"b/x/3".to_vec(), 2),
+                (b"c/d".to_vec(), 1),
+            ],
+            vec![
+                (b"b/x/1".to_vec(), 11),
+                (b"b/x/2".to_vec(), 11),
+                (b"b/x/3".to_vec(), 12),
+                (b"c/d".to_vec(), 2),
+            ],
+            vec![
+                (b"b".to_vec(), 3),
+                (b"b/x/1".to_vec(), 11),
+                (b"b/x/2".to_vec(), 11),
+                (b"b/x/3".to_vec(), 12),
+            ],
+        ];
+        for (version, expected) in versions.iter().zip(expected) {
+            assert_eq!(version.len() as usize, expected.len());
+            assert_eq!(snapshot(version), expected);
+        }
+    }
+
+    #[test]
+    fn test_structural_sharing() {
+        let child = |tree: &Tree<u32>, label: u8| tree.root().get_edge(&label, &Binary).unwrap().1;
+
+        let mut txn = Tree::<u32>::new().start_transaction();
+        for key in ["a/x/1", "a/x/2", "b/x/1", "b/x/2", "c"] {
+            txn.insert(key, 1);
+        }
+        let tree = txn.commit();
+
+        // starting a transaction copies nothing
+        let txn = tree.start_transaction();
+        assert!(Arc::ptr_eq(&txn.root(), &tree.root()));
+        assert_eq!(txn.cloned_nodes(), 0);
+
+        // an insert only copies the path to the key: the root and the `a/x/` node
+        let mut txn = tree.start_transaction();
+        txn.insert("a/x/3", 2);
+        assert_eq!(txn.cloned_nodes(), 2);
+        let inserted = txn.commit();
+        assert!(!Arc::ptr_eq(&inserted.root(), &tree.root()));
+        assert!(!Arc::ptr_eq(&child(&inserted, b'a'), &child(&tree, b'a')));
+        assert!(Arc::ptr_eq(&child(&inserted, b'b'), &child(&tree, b'b')));
+        assert!(Arc::ptr_eq(&child(&inserted, b'c'), &child(&tree, b'c')));
+
+        // so do deletes and prefix updates
+        let mut txn = inserted.start_transaction();
+        txn.delete("c");
+        txn.update_prefix("a/", |_, value| value * 10);
+        let updated = txn.commit();
+        assert!(Arc::ptr_eq(&child(&updated, b'b'), &child(&tree, b'b')));
+        assert_eq!(updated.get("a/x/3"), Some(20));
+        assert_eq!(inserted.get("a/x/3"), Some(2));
+        assert_eq!(updated.get("c"), None);
+        assert_eq!(tree.get("c"), Some(1));
+
+        // cloning a tree shares its whole structure
+        let cloned = updated.clone();
+        assert!(Arc::ptr_eq(&cloned.root(), &updated.root()));
+    }
+
+    #[test]
+    fn test_iter() {
+        let mut txn = Tree::<u32>::new().start_transaction();
+        let insert_keys = [
+            "foo/bar/baz",
+            "foo/baz/bar",
+            "foo/zip/zap",
+            "foo",
+            "",
+            "zipzap",
+            "foobar",
+        ];
+        for (i, key) in insert_keys.iter().enumerate() {
+            txn.insert(key, i as u32);
+        }
+        let tree = txn.commit();
+
+        assert_eq!(
+            keys(tree.iter()),
+            vec![
+                "",
+                "foo",
+                "foo/bar/baz",
+                "foo/baz/bar",
+                "foo/zip/zap",
+                "foobar",
+                "zipzap"
+            ]
+        );
+        assert_eq!(
+            tree.iter()
+                .map(|leaf| *leaf.get_value())
+                .collect::<Vec<_>>(),
+            vec![4, 3, 0, 1, 2, 6, 5]
+        );
+        assert_eq!(keys(Tree::<u32>::new().iter()), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_iter_prefix() {
+        let mut txn = Tree::<u32>::new().start_transaction();
+        for key in [
+            "foo/bar/baz",
+            "foo/baz/bar",
+            "foo/zip/zap",
+            "foobar",
+            "zipzap",
+        ] {
+            txn.insert(key, 0);
+        }
+        let tree = txn.commit();
+
+        let cases: [(&str, Vec<&str>); 8] = [
+            (
+                "",
+                vec![
+                    "foo/bar/baz",
+                    "foo/baz/bar",
+                    "foo/zip/zap",
+                    "foobar",
+                    "zipzap",
+                ],
+            ),
+            (
+                "f",
+                vec!["foo/bar/baz", "foo/baz/bar", "foo/zip/zap", "foobar"],
+            ),
+            (
+                "foo",
+                vec!["foo/bar/baz", "foo/baz/bar", "foo/zip/zap", "foobar"],
+            ),
+            ("foo/", vec!["foo/bar/baz", "foo/baz/bar", "foo/zip/zap"]),
+            ("foo/b", vec!["foo/bar/baz", "foo/baz/bar"]),
+            ("foo/ba", vec!["foo/bar/baz", "foo/baz/bar"]),
+            ("foo/bar/baz", vec!["foo/bar/baz"]),
+            ("foo/bar/bazoo", vec![]),
+        ];
+        for (prefix, expected) in cases {
+            assert_eq!(
+                keys(tree.iter_prefix(prefix)),
+                expected,
+                "prefix '{prefix}'"
+            );
+        }
+        assert_eq!(keys(tree.iter_prefix("z")), vec!["zipzap"]);
+        assert_eq!(keys(tree.iter_prefix("zz")), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_range() {
+        let mut txn = Tree::<u32>::new().start_transaction();
+        let insert_keys = [
+            "00000", "00001", "00004", "00010", "00020", "20020", "foo", "foobar",
+        ];
+        for key in insert_keys {
+            txn.insert(key, 0);
+        }
+        let tree = txn.commit();
+
+        assert_eq!(keys(tree.range::<&str>(..)), insert_keys);
+        assert_eq!(
+            keys(tree.range("00001".."00020")),
+            vec!["00001", "00004", "00010"]
+        );
+        assert_eq!(
+            keys(tree.range("00001"..="00020")),
+            vec!["00001", "00004", "00010", "00020"]
+        );
+        assert_eq!(keys(tree.range("00002".."00011")), vec!["00004", "00010"]);
+        assert_eq!(
+            keys(tree.range("0".."1")),
+            vec!["00000", "00001", "00004", "00010", "00020"]
+        );
+        assert_eq!(
+            keys(tree.range("00020"..)),
+            vec!["00020", "20020", "foo", "foobar"]
+        );
+        assert_eq!(keys(tree.range("fo"..)), vec!["foo", "foobar"]);
+        assert_eq!(keys(tree.range("foo0"..)), vec!["foobar"]);
+        assert_eq!(keys(tree.range("g"..)), Vec::<String>::new());
+        assert_eq!(keys(tree.range(.."00004")), vec!["00000", "00001"]);
+        assert_eq!(
+            keys(tree.range::<&str>((Bound::Excluded("00001"), Bound::Included("00010")))),
+            vec!["00004", "00010"]
+        );
+    }
+
+    #[test]
+    fn test_natural_iteration() {
+        let mut txn = Tree::<u32>::with_collation(Natural).start_transaction();
+        for key in ["file10", "file2", "file1", "file100", "file20", "other"] {
+            txn.insert(key, 0);
+        }
+        let tree = txn.commit();
+
+        assert_eq!(
+            keys(tree.iter()),
+            vec!["file1", "file2", "file10", "file20", "file100", "other"]
+        );
+        assert_eq!(keys(tree.range("file2".."file20")), vec!["file2", "file10"]);
+        assert_eq!(
+            keys(tree.range("file3"..)),
+            vec!["file10", "file20", "file100", "other"]
+        );
+        assert_eq!(keys(tree.iter_prefix("file")).len(), 5);
+    }
+
+    /// Returns the keys of the frozen leaves as strings, in order.
+    fn frozen_keys<'a, T: Clone + 'a>(
+        leaves: impl Iterator<Item = (&'a [u8], &'a T)>,
+    ) -> Vec<String> {
+        leaves
+            .map(|(key, _)| String::from_utf8(key.to_vec()).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_freeze() {
+        let mut txn = Tree::<u32>::new().with_aggregate(Sum).start_transaction();
+        let insert_keys = [
+            "", "00000", "00001", "00004", "00010", "00020", "20020", "foo", "foobar", "foobaz",
+        ];
+        for (i, key) in insert_keys.iter().enumerate() {
+            txn.insert(key, i as u32);
+        }
+        let tree = txn.commit();
+        let frozen = tree.freeze();
+
+        assert_eq!(frozen.len(), tree.len());
+        for (i, key) in insert_keys.iter().enumerate() {
+            assert_eq!(frozen.get(key), Some(&(i as u32)));
+        }
+        assert_eq!(frozen.get("0000"), None);
+        assert_eq!(frozen.get("foob"), None);
+        assert_eq!(frozen.get("foobarbaz"), None);
+
+        assert_eq!(
+            frozen.longest_prefix("foobarbaz"),
+            Some((b"foobar".as_slice(), &8))
+        );
+        assert_eq!(frozen.longest_prefix("foob"), Some((b"foo".as_slice(), &7)));
+        assert_eq!(frozen.longest_prefix("0001"), Some((b"".as_slice(), &0)));
+
+        assert_eq!(frozen_keys(frozen.iter()), keys(tree.iter()));
+        assert_eq!(frozen.iter().next_back(), Some((b"foobaz".as_slice(), &9)));
+        for prefix in ["", "0", "0000", "00001", "000010", "2", "fo", "foobar", "g"] {
+            assert_eq!(
+                frozen_keys(frozen.iter_prefix(prefix)),
+                keys(tree.iter_prefix(prefix)),
+                "keys with prefix {prefix:?}"
+            );
+        }
+
+        let bounds = [
+            "", "0", "00001", "00002", "00020", "0003", "foo", "foo0", "foobar", "g",
+        ];
+        for start in bounds {
+            for end in bounds {
+                assert_eq!(
+                    frozen_keys(frozen.range(start..end)),
+                    keys(tree.range(start..end)),
+                    "keys in {start:?}..{end:?}"
+                );
+                let range = (Bound::Excluded(start), Bound::Included(end));
+                assert_eq!(
+                    frozen_keys(frozen.range::<&str>(range)),
+                    keys(tree.range::<&str>(range)),
+                    "keys in {range:?}"
+                );
+            }
+        }
+
+        // thawing rebuilds the same tree, with its aggregate
+        let thawed = frozen.thaw();
+        assert_eq!(thawed, tree);
+        assert_eq!(thawed.aggregate_prefix("foo"), tree.aggregate_prefix("foo"));
+        let (thawed, _) = thawed.insert("foobaz0", 10);
+        assert_eq!(thawed.aggregate_prefix("foobaz"), Some(19));
+
+        let empty = Tree::<u32>::new().freeze();
+        assert!(empty.is_empty());
+        assert_eq!(empty.get(""), None);
+        assert_eq!(empty.range::<&str>(..).count(), 0);
+        assert!(empty.thaw().is_empty());
+
+        let mut txn = Tree::<u32>::with_collation(CaseInsensitive).start_transaction();
+        txn.insert("Apple", 1);
+        txn.insert("apricot", 2);
+        let frozen = txn.commit().freeze();
+        assert_eq!(frozen.get("APPLE"), Some(&1));
+        assert_eq!(
+            frozen_keys(frozen.iter_prefix("AP")),
+            vec!["Apple", "apricot"]
+        );
+    }
+
+    /// Returns the addresses of every node of the tree.
+    fn node_addresses(tree: &Tree<u32>) -> Vec<usize> {
+        let root = tree.root();
+        let mut addresses = Vec::new();
+        let mut stack = vec![root.as_ref()];
+        while let Some(node) = stack.pop() {
+            addresses.push(node as *const Node<u32> as usize);
+            stack.extend(node.edges().iter().map(|edge| edge.get_node()));
+        }
+        addresses.sort();
+        addresses
+    }
+
+    #[test]
+    fn test_node_arena() {
+        let arena = NodeArena::new();
+        let build = |count: u32| {
+            let mut txn = Tree::new().with_arena(&arena).start_transaction();
+            for i in 0..count {
+                txn.insert(format!("key/{:02}/{i}", i % 7), i);
+            }
+            txn.commit()
+        };
+
+        let tree = build(100);
+        let root_address = Arc::as_ptr(&tree.root()) as usize;
+        let addresses: Vec<_> = node_addresses(&tree)
+            .into_iter()
+            .filter(|address| *address != root_address)
+            .collect();
+        let (watched, _) = tree.get_watch("key/00/0");
+        drop(tree);
+
+        // the next tree is built from the nodes below the root of the dropped one,
+        // whose root is dropped with it
+        let tree = build(100);
+        let reused = node_addresses(&tree)
+            .into_iter()
+            .filter(|address| addresses.contains(address))
+            .count();
+        assert_eq!(reused, addresses.len());
+        assert_eq!(format!("{arena:?}"), "NodeArena { released: 0 }");
+        for i in 0..100 {
+            assert_eq!(tree.get(format!("key/{:02}/{i}", i % 7)), Some(i));
+        }
+        assert!(
+            !watched.is_fired(),
+            "reused nodes must not fire old watches"
+        );
+
+        // nodes still shared with another tree are not reused
+        let kept = tree.clone();
+        let (updated, _) = tree.insert("key/01/1", 1000);
+        let shared = node_addresses(&kept);
+        drop(kept);
+        let (updated, _) = updated.delete_prefix("key/0");
+        assert_eq!(updated.len(), 0);
+        let rebuilt = build(100);
+        let reused = node_addresses(&rebuilt);
+        for i in 0..100 {
+            assert_eq!(rebuilt.get(format!("key/{:02}/{i}", i % 7)), Some(i));
+        }
+        assert!(reused.iter().any(|address| shared.contains(address)));
+
+        arena.clear();
+        assert_eq!(format!("{arena:?}"), "NodeArena { released: 0 }");
+    }
+
+    #[test]
+    fn test_node_arena_release() {
+        let arena = NodeArena::new();
+        let value = Arc::new(0);
+        let mut txn = Tree::new().with_arena(&arena).start_transaction();
+        for i in 0..100 {
+            txn.insert(format!("key/{:02}/{i}", i % 7), Arc::new(i));
+        }
+        txn.insert("value", value.clone());
+        let tree = txn.commit();
+
+        // a handle dropped while another one holds the root releases nothing
+        drop(tree.clone());
+        assert_eq!(format!("{arena:?}"), "NodeArena { released: 0 }");
+        let mut txn = tree.start_transaction();
+        drop(tree);
+        txn.insert("key/00/0", value.clone());
+        assert_eq!(
+            txn.cloned_nodes(),
+            0,
+            "nodes owned by the transaction are modified in place"
+        );
+        let tree = txn.commit();
+
+        // the values of a released tree are dropped right away
+        assert_eq!(Arc::strong_count(&value), 3);
+        drop(tree);
+        assert_eq!(Arc::strong_count(&value), 1);
+        assert_ne!(format!("{arena:?}"), "NodeArena { released: 0 }");
+    }
+
+    #[test]
+    fn test_very_deep_tree() {
+        // small enough a stack for any recursion over the depth of the tree to overflow it
+        let handle = std::thread::Builder::new()
+            .stack_size(256 * 1024)
+            .spawn(|| {
+                // keys inserted byte by byte in increasing lengths nest a node per byte
+                let depth = 5000;
+                let mut txn = Tree::<usize>::new().start_transaction();
+                for len in 1..=depth {
+                    txn.insert("a".repeat(len), len);
+                }
+                let tree = txn.commit();
+                assert_eq!(tree.len(), depth as u32);
+                assert_eq!(tree.get("a".repeat(depth)), Some(depth));
+                assert_eq!(tree.iter().count(), depth);
+
+                // whole tree traversals
+                let mapped = tree.map(|value| *value);
+                assert!(mapped == tree, "a copy of the tree should be equal to it");
+                let mut txn = mapped.start_transaction();
+                assert!(txn.update_prefix("a", |_, value| value + 1));
+                let updated = txn.commit();
+                assert_eq!(updated.get("a".repeat(depth)), Some(depth + 1));
+                assert!(updated != tree);
+                let even = updated.filter(|_, value| value % 2 == 0);
+                assert_eq!(even.len(), (depth / 2) as u32);
+                assert_eq!(even.get("a".repeat(depth - 1)), Some(depth));
+                drop(even);
+
+                // each update copies the whole path of the key
+                let (tree, _) = tree.insert(format!("{}b", "a".repeat(depth)), 0);
+                let (tree, old) = tree.delete("a".repeat(depth / 2));
+                assert_eq!(old, Some(depth / 2));
+                let (shallow, deleted) = tree.clone().delete_prefix("a".repeat(depth / 4));
+                assert!(deleted);
+                assert_eq!(shallow.len(), (depth / 4 - 1) as u32);
+                assert_eq!(tree.len(), depth as u32);
+                drop(tree);
+                assert_eq!(shallow.get("a".repeat(depth / 4 - 1)), Some(depth / 4 - 1));
+
+                // keys several megabytes long
+                let long = "x".repeat(4 * 1024 * 1024);
+                let (tree, _) = shallow.insert(&long, 1);
+                let (tree, _) = tree.insert(format!("{long}y"), 2);
+                let (tree, _) = tree.insert(format!("{}z", &long[1..]), 3);
+                assert_eq!(tree.get(&long), Some(1));
+                assert_eq!(tree.get(format!("{long}y")), Some(2));
+                assert_eq!(tree.get(format!("{}z", &long[1..])), Some(3));
+                let (tree, old) = tree.delete(&long);
+                assert_eq!(old, Some(1));
+                assert_eq!(tree.get(format!("{long}y")), Some(2));
+                let (tree, deleted) = tree.delete_prefix("x");
+                assert!(deleted);
+                assert_eq!(tree.len(), (depth / 4 - 1) as u32);
+            })
+            .unwrap();
+        handle.join().unwrap();
+    }
 }
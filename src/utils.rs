@@ -1,12 +1,22 @@
-/// Returns the length of the longest common prefix of two strings.
-pub(crate) fn longest_prefix(key1: &str, key2: &str) -> usize {
+use std::{ops::Bound, sync::Arc};
+
+use crate::collation::Collation;
+
+/// Returns the owned, normalized version of a range bound.
+pub(crate) fn normalize_bound<K: AsRef<[L]>, L: EdgeLabel>(
+    bound: Bound<&K>,
+    collation: &dyn Collation<L>,
+) -> Bound<Vec<L>> {
+    bound.map(|key| collation.normalize(key.as_ref()).into_owned())
+}
+
+/// Returns the length of the longest common prefix of two keys.
+pub(crate) fn longest_prefix<L: EdgeLabel>(key1: &[L], key2: &[L]) -> usize {
     let max_len = key1.len().min(key2.len());
 
-    let key1_bytes = key1.as_bytes();
-    let key2_bytes = key2.as_bytes();
     let mut i = 0;
     while i < max_len {
-        if key1_bytes[i] != key2_bytes[i] {
+        if key1[i] != key2[i] {
             break;
         }
         i += 1;
@@ -14,7 +24,51 @@ pub(crate) fn longest_prefix(key1: &str, key2: &str) -> usize {
     i
 }
 
-pub trait NodeValue = Default + std::fmt::Debug + Clone + std::hash::Hash + PartialEq + Eq;
+/// A value stored in the tree.
+/// Values are cloned out of the tree on reads, the tree itself never compares or hashes them.
+pub trait NodeValue = Clone;
+
+/// A symbol of a key, used as the label of the edges in the tree.
+/// Keys are sequences of labels, e.g. bytes, chars, path segments or interned token IDs.
+pub trait EdgeLabel: std::fmt::Debug + Clone + std::hash::Hash + Ord {
+    /// Returns the label as a byte if labels are bytes, large nodes index such edges directly.
+    fn as_byte(&self) -> Option<u8> {
+        None
+    }
+}
+
+impl EdgeLabel for u8 {
+    fn as_byte(&self) -> Option<u8> {
+        Some(*self)
+    }
+}
+
+macro_rules! impl_edge_label {
+    ($($label:ty),* $(,)?) => {
+        $(impl EdgeLabel for $label {})*
+    };
+}
+
+impl_edge_label!(
+    u16,
+    u32,
+    u64,
+    u128,
+    usize,
+    i8,
+    i16,
+    i32,
+    i64,
+    i128,
+    isize,
+    char,
+    bool,
+    String,
+    Box<str>,
+    Arc<str>,
+);
+
+impl<L: EdgeLabel> EdgeLabel for Vec<L> {}
 
 #[cfg(test)]
 mod tests {
@@ -22,10 +76,17 @@ mod tests {
 
     #[test]
     fn test_longest_prefix() {
-        assert_eq!(longest_prefix("hello", "helicopter"), 3);
-        assert_eq!(longest_prefix("test", "testing"), 4);
-        assert_eq!(longest_prefix("abc", "xyz"), 0);
-        assert_eq!(longest_prefix("", "nonempty"), 0);
-        assert_eq!(longest_prefix("same", "same"), 4);
+        assert_eq!(longest_prefix(b"hello", b"helicopter"), 3);
+        assert_eq!(longest_prefix(b"test", b"testing"), 4);
+        assert_eq!(longest_prefix(b"abc", b"xyz"), 0);
+        assert_eq!(longest_prefix(b"", b"nonempty"), 0);
+        assert_eq!(longest_prefix(b"same", b"same"), 4);
+
+        let path = |p: &str| p.split('/').map(String::from).collect::<Vec<_>>();
+        assert_eq!(
+            longest_prefix(&path("usr/local/bin"), &path("usr/local/lib")),
+            2
+        );
+        assert_eq!(longest_prefix(&path("usr/lib"), &path("usr/libexec")), 1);
     }
 }
@@ -0,0 +1,205 @@
+use std::{
+    fmt::{self, Debug},
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Condvar, LazyLock, Mutex, MutexGuard, OnceLock},
+    task::{Context, Poll, Waker},
+    time::Duration,
+};
+
+#[derive(Default)]
+struct State {
+    fired: bool,
+    wakers: Vec<Waker>,
+}
+
+#[derive(Default)]
+struct Shared {
+    state: Mutex<State>,
+    condvar: Condvar,
+}
+
+/// A handle which fires once a committed transaction changes what it watches.
+///
+/// A watch fires only once and stays fired, so a new watch must be taken from the new tree to keep
+/// watching. It can be waited on by blocking the current thread, or awaited as a future.
+#[derive(Clone, Default)]
+pub struct Watch {
+    shared: Arc<Shared>,
+}
+
+impl Watch {
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.shared
+            .state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Fires the watch, waking up everyone waiting on it.
+    pub(crate) fn fire(&self) {
+        let mut state = self.state();
+        if state.fired {
+            return;
+        }
+        state.fired = true;
+        for waker in state.wakers.drain(..) {
+            waker.wake();
+        }
+        self.shared.condvar.notify_all();
+    }
+
+    /// Returns true if the watch has fired.
+    pub fn is_fired(&self) -> bool {
+        self.state().fired
+    }
+
+    /// Blocks the current thread until the watch fires.
+    pub fn wait(&self) {
+        let mut state = self.state();
+        while !state.fired {
+            state = self
+                .shared
+                .condvar
+                .wait(state)
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+        }
+    }
+
+    /// Blocks the current thread until the watch fires or the timeout elapses.
+    /// Returns true if the watch has fired.
+    pub fn wait_timeout(&self, timeout: Duration) -> bool {
+        let state = self.state();
+        let (state, _) = self
+            .shared
+            .condvar
+            .wait_timeout_while(state, timeout, |state| !state.fired)
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        state.fired
+    }
+}
+
+/// A watch fired already, handed out for the signals fired before anyone watched them.
+static FIRED: LazyLock<Watch> = LazyLock::new(|| {
+    let watch = Watch::default();
+    watch.fire();
+    watch
+});
+
+/// The signal of a node or leaf, whose watch is only created once someone asks for it, so
+/// unwatched trees do not allocate any.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct Signal(OnceLock<Watch>);
+
+impl Signal {
+    /// Returns the watch of the signal, creating it on the first call.
+    pub(crate) fn watch(&self) -> Watch {
+        self.0.get_or_init(Watch::default).clone()
+    }
+
+    /// Returns the watch of the signal if anyone asked for it.
+    pub(crate) fn get(&self) -> Option<&Watch> {
+        self.0.get()
+    }
+
+    /// Fires the signal. A watch asked for afterwards has fired already.
+    pub(crate) fn fire(&self) {
+        self.0.get_or_init(|| FIRED.clone()).fire();
+    }
+
+    /// Resets the signal to an unwatched one, returning its watch if anyone asked for it.
+    pub(crate) fn take(&mut self) -> Option<Watch> {
+        self.0.take()
+    }
+}
+
+impl Future for Watch {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let mut state = self.state();
+        if state.fired {
+            return Poll::Ready(());
+        }
+        if !state.wakers.iter().any(|waker| waker.will_wake(cx.waker())) {
+            state.wakers.push(cx.waker().clone());
+        }
+        Poll::Pending
+    }
+}
+
+impl Debug for Watch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Watch")
+            .field("fired", &self.is_fired())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::atomic::{AtomicBool, Ordering},
+        task::Wake,
+        thread,
+    };
+
+    use super::*;
+
+    struct Flag(AtomicBool);
+
+    impl Wake for Flag {
+        fn wake(self: Arc<Self>) {
+            self.0.store(true, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn test_watch_wait() {
+        let watch = Watch::default();
+        assert!(!watch.is_fired());
+        assert!(!watch.wait_timeout(Duration::from_millis(1)));
+
+        let handle = {
+            let watch = watch.clone();
+            thread::spawn(move || watch.wait())
+        };
+        watch.fire();
+        handle.join().unwrap();
+        assert!(watch.is_fired());
+        assert!(watch.wait_timeout(Duration::from_millis(1)));
+    }
+
+    #[test]
+    fn test_signal() {
+        let mut signal = Signal::default();
+        assert!(
+            signal.get().is_none(),
+            "watch should only be created on demand"
+        );
+        let watch = signal.watch();
+        assert!(!watch.is_fired());
+        signal.fire();
+        assert!(watch.is_fired());
+        assert!(signal.take().is_some());
+        assert!(signal.get().is_none());
+
+        // a signal fired before being watched hands out a fired watch
+        let signal = Signal::default();
+        signal.fire();
+        assert!(signal.watch().is_fired());
+    }
+
+    #[test]
+    fn test_watch_future() {
+        let flag = Arc::new(Flag(AtomicBool::new(false)));
+        let waker = Waker::from(flag.clone());
+        let mut cx = Context::from_waker(&waker);
+
+        let mut watch = Watch::default();
+        assert_eq!(Pin::new(&mut watch).poll(&mut cx), Poll::Pending);
+        watch.clone().fire();
+        assert!(flag.0.load(Ordering::SeqCst), "waker should be woken");
+        assert_eq!(Pin::new(&mut watch).poll(&mut cx), Poll::Ready(()));
+    }
+}